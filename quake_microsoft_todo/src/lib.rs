@@ -1,14 +1,17 @@
 #[macro_use]
 extern crate serde;
 
+pub mod onenote;
+pub mod paging;
 pub mod tasks;
 pub mod user;
 
 pub type Date = String;
 pub type DateTimeOffset = String;
 
-/// Contains the `@odata.context` and `@odata.nextLink` properties of a Collection.
-/// The latter contains the URL for the next page of results within the Collection.
+/// Contains the `@odata.context`, `@odata.nextLink` and `@odata.deltaLink` properties of a Collection.
+/// The `next_link` contains the URL for the next page of results within the Collection;
+/// the last page of a delta query has a `delta_link` instead, from which later changes can be requested.
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct OData {
     #[serde(rename = "@odata.context")]
@@ -16,6 +19,9 @@ pub struct OData {
 
     #[serde(rename = "@odata.nextLink")]
     pub next_link: Option<String>,
+
+    #[serde(rename = "@odata.deltaLink")]
+    pub delta_link: Option<String>,
 }
 
 /// A general collection of results from the Graph API.
@@ -27,6 +33,29 @@ pub struct Collection<T> {
     pub odata: OData,
 }
 
+/// An item of a delta query: either an added or changed item, or the id of a removed one.
+///
+/// See: https://docs.microsoft.com/en-us/graph/delta-query-overview
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(untagged)]
+pub enum Delta<T> {
+    Removed {
+        id: String,
+
+        #[serde(rename = "@removed")]
+        removed: Removed,
+    },
+
+    Changed(T),
+}
+
+/// The `@removed` annotation of an item removed since the last delta query.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Removed {
+    /// Usually `deleted`, or `changed` when the item was moved out of the queried collection.
+    pub reason: Option<String>,
+}
+
 /// Represents a `dateTimeTimeZone` resource type.
 ///
 /// @TODO: This could probably be converted into a `chrono` type with
@@ -0,0 +1,241 @@
+use crate::DateTimeOffset;
+
+/// Represents a OneNote notebook.
+///
+/// Graph leaves out properties which weren't `$select`ed, and only returns a few of them on the
+/// `parentNotebook` of sections and pages, so everything but the id is optional.
+///
+/// See: https://docs.microsoft.com/en-us/graph/api/resources/notebook?view=graph-rest-1.0
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct Notebook {
+    /// The unique identifier of the notebook. Read-only.
+    pub id: String,
+
+    /// The name of the notebook.
+    pub display_name: Option<String>,
+
+    /// The date and time when the notebook was created, in ISO 8601 format and always in UTC. Read-only.
+    pub created_date_time: Option<DateTimeOffset>,
+
+    /// The date and time when the notebook was last modified, in ISO 8601 format and always in UTC. Read-only.
+    pub last_modified_date_time: Option<DateTimeOffset>,
+
+    /// Indicates whether this is the user's default notebook. Read-only.
+    pub is_default: Option<bool>,
+
+    /// Indicates whether the notebook is shared. Read-only.
+    pub is_shared: Option<bool>,
+
+    /// The role of the user on the notebook. Read-only.
+    pub user_role: Option<OnenoteUserRole>,
+
+    /// The endpoint where you can get details about the notebook. Read-only.
+    #[serde(rename = "self")]
+    pub self_url: Option<String>,
+
+    /// The url for the `sections` navigation property, which returns all the sections in the notebook. Read-only.
+    pub sections_url: Option<String>,
+
+    /// The url for the `sectionGroups` navigation property, which returns all the section groups in the notebook. Read-only.
+    pub section_groups_url: Option<String>,
+
+    /// Links for opening the notebook.
+    pub links: Option<Links>,
+}
+
+/// The possible values of a `Notebook` `user_role`.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub enum OnenoteUserRole {
+    Owner,
+    Contributor,
+    Reader,
+    None,
+
+    /// Any value not known to this version of the model.
+    #[serde(other)]
+    Unknown,
+}
+
+/// Represents a section group in a OneNote notebook, which may contain sections and other section groups.
+///
+/// See: https://docs.microsoft.com/en-us/graph/api/resources/sectiongroup?view=graph-rest-1.0
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct SectionGroup {
+    /// The unique identifier of the section group. Read-only.
+    pub id: String,
+
+    /// The name of the section group.
+    pub display_name: Option<String>,
+
+    /// The date and time when the section group was created, in ISO 8601 format and always in UTC. Read-only.
+    pub created_date_time: Option<DateTimeOffset>,
+
+    /// The date and time when the section group was last modified, in ISO 8601 format and always in UTC. Read-only.
+    pub last_modified_date_time: Option<DateTimeOffset>,
+
+    /// The endpoint where you can get details about the section group. Read-only.
+    #[serde(rename = "self")]
+    pub self_url: Option<String>,
+
+    /// The url for the `sections` navigation property, which returns all the sections in the section group. Read-only.
+    pub sections_url: Option<String>,
+
+    /// The url for the `sectionGroups` navigation property, which returns all the section groups in the section group. Read-only.
+    pub section_groups_url: Option<String>,
+
+    /// The notebook that contains the section group. Read-only.
+    pub parent_notebook: Option<Notebook>,
+
+    /// The section group that contains the section group, if any. Read-only.
+    pub parent_section_group: Option<Box<SectionGroup>>,
+}
+
+/// Represents a section in a OneNote notebook, which contains pages.
+///
+/// See: https://docs.microsoft.com/en-us/graph/api/resources/onenotesection?view=graph-rest-1.0
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct OnenoteSection {
+    /// The unique identifier of the section. Read-only.
+    pub id: String,
+
+    /// The name of the section.
+    pub display_name: Option<String>,
+
+    /// The date and time when the section was created, in ISO 8601 format and always in UTC. Read-only.
+    pub created_date_time: Option<DateTimeOffset>,
+
+    /// The date and time when the section was last modified, in ISO 8601 format and always in UTC. Read-only.
+    pub last_modified_date_time: Option<DateTimeOffset>,
+
+    /// Indicates whether this is the user's default section. Read-only.
+    pub is_default: Option<bool>,
+
+    /// The endpoint where you can get details about the section. Read-only.
+    #[serde(rename = "self")]
+    pub self_url: Option<String>,
+
+    /// The url for the `pages` navigation property, which returns all the pages in the section. Read-only.
+    pub pages_url: Option<String>,
+
+    /// Links for opening the section.
+    pub links: Option<Links>,
+
+    /// The notebook that contains the section. Read-only.
+    pub parent_notebook: Option<Notebook>,
+
+    /// The section group that contains the section, if any. Read-only.
+    pub parent_section_group: Option<SectionGroup>,
+}
+
+/// Represents a page in a OneNote section.
+///
+/// See: https://docs.microsoft.com/en-us/graph/api/resources/onenotepage?view=graph-rest-1.0
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct OnenotePage {
+    /// The unique identifier of the page. Read-only.
+    pub id: String,
+
+    /// The title of the page.
+    pub title: Option<String>,
+
+    /// The date and time when the page was created, in ISO 8601 format and always in UTC. Read-only.
+    pub created_date_time: Option<DateTimeOffset>,
+
+    /// The date and time when the page was last modified, in ISO 8601 format and always in UTC. Read-only.
+    pub last_modified_date_time: Option<DateTimeOffset>,
+
+    /// The unique identifier of the application that created the page. Read-only.
+    pub created_by_app_id: Option<String>,
+
+    /// The url for the page's HTML content. Read-only.
+    pub content_url: Option<String>,
+
+    /// The indentation level of the page. Read-only.
+    pub level: Option<i32>,
+
+    /// The order of the page within its parent section. Read-only.
+    pub order: Option<i32>,
+
+    /// The endpoint where you can get details about the page. Read-only.
+    #[serde(rename = "self")]
+    pub self_url: Option<String>,
+
+    /// Links for opening the page.
+    pub links: Option<Links>,
+
+    /// The notebook that contains the page. Read-only.
+    pub parent_notebook: Option<Notebook>,
+
+    /// The section that contains the page. Read-only.
+    pub parent_section: Option<OnenoteSection>,
+}
+
+/// Represents an image or other file resource of a OneNote page.
+///
+/// Pages reference their resources by the `.../resources/{id}/$value` url of their content.
+///
+/// See: https://docs.microsoft.com/en-us/graph/api/resources/resource?view=graph-rest-1.0
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct OnenoteResource {
+    /// The unique identifier of the resource. Read-only.
+    pub id: String,
+
+    /// The endpoint where you can get details about the resource. Read-only.
+    #[serde(rename = "self")]
+    pub self_url: Option<String>,
+
+    /// The url for downloading the content of the resource. Read-only.
+    pub content_url: Option<String>,
+}
+
+impl OnenoteResource {
+    /// The resource whose content is served at `url`, if it is a OneNote resource url.
+    ///
+    /// Only the path of `url` is looked at: check that it is on the Graph endpoint before requesting it
+    /// with an access token.
+    ///
+    /// ```
+    /// use quake_microsoft_todo::onenote::OnenoteResource;
+    ///
+    /// let url = "https://graph.microsoft.com/v1.0/me/onenote/resources/0-8a9f!1-34/$value";
+    /// assert_eq!(OnenoteResource::from_content_url(url).unwrap().id, "0-8a9f!1-34");
+    /// assert!(OnenoteResource::from_content_url("https://example.com/image.png").is_none());
+    /// ```
+    pub fn from_content_url(url: &str) -> Option<Self> {
+        let (base, rest) = url.split_once("/resources/")?;
+        let (id, value) = rest.split_once('/')?;
+        if value != "$value" || id.is_empty() {
+            return None;
+        }
+
+        Some(OnenoteResource {
+            id: id.to_string(),
+            self_url: Some(format!("{}/resources/{}", base, id)),
+            content_url: Some(url.to_string()),
+        })
+    }
+}
+
+/// Links for opening a notebook, section or page in the OneNote client or in OneNote on the web.
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct Links {
+    /// Opens the item in the OneNote native client, if it's installed.
+    pub one_note_client_url: Option<ExternalLink>,
+
+    /// Opens the item in OneNote on the web.
+    pub one_note_web_url: Option<ExternalLink>,
+}
+
+/// A url which opens an item outside of Graph.
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct ExternalLink {
+    /// The url of the link.
+    pub href: Option<String>,
+}
@@ -0,0 +1,79 @@
+use std::vec::IntoIter;
+
+use crate::Collection;
+
+/// An iterator over every item of a paged `Collection`, which fetches the pages lazily
+/// by following their `@odata.nextLink`.
+///
+/// The pages are fetched by the given `fetch` function, so that any HTTP client can be used:
+///
+/// ```
+/// use quake_microsoft_todo::Collection;
+/// use quake_microsoft_todo::paging::Pager;
+///
+/// let fetch = |url: &str| serde_json::from_str::<Collection<u32>>(match url {
+///     "page-1" => r#"{ "value": [1, 2], "@odata.nextLink": "page-2" }"#,
+///     _ => r#"{ "value": [3] }"#,
+/// });
+///
+/// let items: Result<Vec<u32>, _> = Pager::new("page-1", fetch).collect();
+/// assert_eq!(items.unwrap(), vec![1, 2, 3]);
+/// ```
+///
+/// Each item is yielded as a `Result`; once fetching a page fails, the error is yielded and
+/// the iteration ends.
+pub struct Pager<T, F> {
+    /// Fetches the page at the given url.
+    fetch: F,
+
+    /// The url of the next page to fetch, if there is one.
+    next_url: Option<String>,
+
+    /// The items of the current page which haven't been yielded yet.
+    items: IntoIter<T>,
+
+    /// The delta link of the last page, when paging through the results of a delta query.
+    delta_link: Option<String>,
+}
+
+impl<T, F> Pager<T, F> {
+    /// Create a pager over the collection at `url`. Nothing is fetched until the first item is requested.
+    pub fn new<S: Into<String>>(url: S, fetch: F) -> Self {
+        Self {
+            fetch,
+            next_url: Some(url.into()),
+            items: Vec::new().into_iter(),
+            delta_link: None,
+        }
+    }
+
+    /// The `@odata.deltaLink` of the last page, once every item of a delta query has been read.
+    pub fn delta_link(&self) -> Option<&str> {
+        self.delta_link.as_deref()
+    }
+}
+
+impl<T, E, F> Iterator for Pager<T, F>
+    where F: FnMut(&str) -> Result<Collection<T>, E>
+{
+    type Item = Result<T, E>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(item) = self.items.next() {
+                return Some(Ok(item));
+            }
+
+            // A page may be empty while still linking to further pages.
+            let url = self.next_url.take()?;
+            match (self.fetch)(&url) {
+                Ok(page) => {
+                    self.next_url = page.odata.next_link;
+                    self.delta_link = page.odata.delta_link;
+                    self.items = page.value.into_iter();
+                }
+                Err(e) => return Some(Err(e)),
+            }
+        }
+    }
+}
@@ -1,3 +1,5 @@
+use serde_json::{Map, Value};
+
 use crate::{Date, DateTimeOffset, DateTimeTimeZone};
 
 /// Represents a single Todo List.
@@ -14,23 +16,31 @@ pub struct TodoTaskList {
     pub id: String,
 
     /// True if the user is owner of the given task list.
+    #[serde(default)]
     pub is_owner: bool,
 
     /// True if the task list is shared with other users
+    #[serde(default)]
     pub is_shared: bool,
 
     /// Property indicating the list name if the given list is a well-known list. 
+    #[serde(default)]
     pub wellknown_list_name: WellknownListName,
 }
 
 /// The possible values of a `TodoTaskList` `wellknown_list_name`. 
-#[derive(Serialize, Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
 #[serde(rename_all = "camelCase")]
 pub enum WellknownListName {
+    #[default]
     None,
     DefaultList,
     FlaggedEmails,
     UnknownFutureValue,
+
+    /// Any value not known to this version of the model.
+    #[serde(other)]
+    Unknown,
 }
 
 /// Represents a single Task within a todo list. 
@@ -40,13 +50,18 @@ pub enum WellknownListName {
 #[serde(rename_all = "camelCase")]
 pub struct TodoTask {
     /// The task body that typically contains information about the task.
+    #[serde(default)]
     pub body: ItemBody,
 
-    /// The date and time when the task was last modified. By default, it is in UTC. 
+    /// The date and time when the task body was last modified. By default, it is in UTC. 
     /// You can provide a custom time zone in the request header. 
     /// The property value uses ISO 8601 format and is always in UTC time. 
     /// For example, midnight UTC on Jan 1, 2020 would look like this: '2020-01-01T00:00:00Z'.
-    pub body_last_modified_date_time: Option<String>,
+    pub body_last_modified_date_time: Option<DateTimeOffset>,
+
+    /// The categories associated with the task.
+    #[serde(default)]
+    pub categories: Vec<String>,
 
     /// The date in the specified time zone that the task was finished.
     pub completed_date_time: Option<DateTimeTimeZone>,
@@ -60,10 +75,17 @@ pub struct TodoTask {
     /// Unique identifier for the task. By default, this value changes when the item is moved from one list to another.
     pub id: String,
 
+    /// Indicates whether the task has attachments.
+    #[serde(default)]
+    pub has_attachments: bool,
+
     /// The importance of the task.
+    #[serde(default)]
     pub importance: Importance,
 
     /// Set to true if an alert is set to remind the user of the task.
+    /// Missing when the task was created by a client which doesn't support reminders.
+    #[serde(default)]
     pub is_reminder_on: bool,
 
     /// The date and time when the task was last modified. By default, it is in UTC. 
@@ -75,54 +97,173 @@ pub struct TodoTask {
     /// The date and time for a reminder alert of the task to occur.
     pub reminder_date_time: Option<DateTimeTimeZone>,
 
+    /// The date in the specified time zone at which the task is scheduled to start.
+    pub start_date_time: Option<DateTimeTimeZone>,
+
     /// Indicates the state or progress of the task. 
+    #[serde(default)]
     pub status: TaskStatus,
 
     /// A brief description of the task.
     pub title: String,
 
-    // TODO: extensions
-    // TODO: linkedResources
+    /// The files attached to the task, without their content. Fetched from `/tasks/{id}/attachments`.
+    #[serde(default)]
+    pub attachments: Vec<TaskFileAttachment>,
+
+    /// The steps of the task. Only returned by Graph when expanded, with `$expand=checklistItems`.
+    #[serde(default)]
+    pub checklist_items: Vec<ChecklistItem>,
+
+    /// The open extensions added to the task by applications. Only returned when expanded, with `$expand=extensions`.
+    #[serde(default)]
+    pub extensions: Vec<Extension>,
+
+    /// The items in other applications, e.g. an Outlook email, which the task was created from or links to.
+    /// Only returned when expanded, with `$expand=linkedResources`.
+    #[serde(default)]
+    pub linked_resources: Vec<LinkedResource>,
+}
+
+/// Represents a subtask (step) of a `TodoTask`.
+///
+/// See: https://docs.microsoft.com/en-us/graph/api/resources/checklistitem?view=graph-rest-1.0
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ChecklistItem {
+    /// The date and time when the checklist item was finished.
+    pub checked_date_time: Option<DateTimeOffset>,
+
+    /// The date and time when the checklist item was created.
+    pub created_date_time: DateTimeOffset,
+
+    /// The title of the checklist item.
+    pub display_name: String,
+
+    /// Unique identifier for the checklist item.
+    pub id: String,
+
+    /// State indicating whether the item is checked off or not.
+    pub is_checked: bool,
 }
 
+/// A file attached to a `TodoTask`. The content is served separately, from `/attachments/{id}/$value`.
+///
+/// See: https://docs.microsoft.com/en-us/graph/api/resources/taskfileattachment?view=graph-rest-1.0
 #[derive(Serialize, Deserialize, Debug, Clone)]
 #[serde(rename_all = "camelCase")]
+pub struct TaskFileAttachment {
+    /// The MIME type of the attachment.
+    pub content_type: Option<String>,
+
+    /// Unique identifier for the attachment.
+    pub id: String,
+
+    /// The date and time when the attachment was last modified.
+    pub last_modified_date_time: Option<DateTimeOffset>,
+
+    /// The name of the attachment, usually its file name.
+    pub name: String,
+
+    /// The length of the attachment in bytes.
+    pub size: i64,
+}
+
+/// Represents an item in a partner application, such as an email or a chat message, related to a `TodoTask`.
+///
+/// See: https://docs.microsoft.com/en-us/graph/api/resources/linkedresource?view=graph-rest-1.0
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct LinkedResource {
+    /// The name of the application the linked resource belongs to.
+    pub application_name: Option<String>,
+
+    /// The title of the linked resource.
+    pub display_name: Option<String>,
+
+    /// The id of the object in the partner application.
+    pub external_id: Option<String>,
+
+    /// Unique identifier for the linked resource.
+    pub id: String,
+
+    /// A deep link to the linked resource.
+    pub web_url: Option<String>,
+}
+
+/// An open extension: untyped properties which an application stored on a `TodoTask`.
+///
+/// See: https://docs.microsoft.com/en-us/graph/api/resources/opentypeextension?view=graph-rest-1.0
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct Extension {
+    /// The fully qualified identifier of the extension.
+    pub id: String,
+
+    /// A unique name for the extension, e.g. `com.contoso.referral`.
+    pub extension_name: Option<String>,
+
+    /// The custom properties of the extension.
+    #[serde(flatten)]
+    pub properties: Map<String, Value>,
+}
+
+/// The possible `status` values for a `TodoTask`.
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[serde(rename_all = "camelCase")]
 pub enum TaskStatus {
+    #[default]
     NotStarted,
     InProgress,
     Completed,
     WaitingOnOthers,
     Deferred,
+
+    /// Any value not known to this version of the model.
+    #[serde(other)]
+    Unknown,
 }
 
 /// Represents properties of the body of an item, such as a message, event or group post.
 /// 
 /// See: https://docs.microsoft.com/en-us/graph/api/resources/itembody?view=graph-rest-1.0
-#[derive(Serialize, Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
 #[serde(rename_all = "camelCase")]
 pub struct ItemBody {
     /// The content of the item.
+    #[serde(default)]
     pub content: String,
 
     /// The type of the content.
+    #[serde(default)]
     pub content_type: BodyType,
 }
 
 /// The possible values of `content_type` for an `ItemBody`.
-#[derive(Serialize, Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
 #[serde(rename_all = "camelCase")]
 pub enum BodyType {
+    #[default]
     Text,
     Html,
+
+    /// Any value not known to this version of the model.
+    #[serde(other)]
+    Unknown,
 }
 
 /// The possible `importance` values for a `TodoTask`. 
-#[derive(Serialize, Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
 #[serde(rename_all = "camelCase")]
 pub enum Importance {
     Low,
+    #[default]
     Normal,
-    High
+    High,
+
+    /// Any value not known to this version of the model.
+    #[serde(other)]
+    Unknown,
 }
 
 /// The recurrence pattern and range for a `TodoTask`. 
@@ -152,6 +293,7 @@ pub enum RecurrencePattern {
     },
 
     /// Event repeats on the same day or days of the week, based on the number of weeks between each set of occurrences.
+    #[serde(rename_all = "camelCase")]
     Weekly { 
         /// The number of units between occurrences, where units can be in days, weeks, months, or years, depending on the type. Required.
         interval: i32, 
@@ -164,6 +306,7 @@ pub enum RecurrencePattern {
     },
 
     /// Event repeats on the specified day of the month (e.g. the 15th), based on the number of months between occurrences.
+    #[serde(rename_all = "camelCase")]
     AbsoluteMonthly { 
         /// The number of units between occurrences, where units can be in days, weeks, months, or years, depending on the type. Required.
         interval: i32, 
@@ -173,6 +316,7 @@ pub enum RecurrencePattern {
     },
     
     /// Event repeats on the specified day or days of the week, in the same relative position in the month, based on the number of months between occurrences.
+    #[serde(rename_all = "camelCase")]
     RelativeMonthly { 
         /// The number of units between occurrences, where units can be in days, weeks, months, or years, depending on the type. Required.
         interval: i32, 
@@ -183,6 +327,7 @@ pub enum RecurrencePattern {
     },
 
     /// Event repeats on the specified day and month, based on the number of years between occurrences.
+    #[serde(rename_all = "camelCase")]
     AbsoluteYearly { 
         /// The number of units between occurrences, where units can be in days, weeks, months, or years, depending on the type. Required.
         interval: i32, 
@@ -195,6 +340,7 @@ pub enum RecurrencePattern {
     },
 
     /// Event repeats on the specified day or days of the week, in the same relative position in a specific month of the year, based on the number of years between occurrences.
+    #[serde(rename_all = "camelCase")]
     RelativeYearly { 
         /// The number of units between occurrences, where units can be in days, weeks, months, or years, depending on the type. Required.
         interval: i32, 
@@ -205,7 +351,11 @@ pub enum RecurrencePattern {
 
         /// The month in which the event occurs. This is a number from 1 to 12.
         month: i32 
-    }
+    },
+
+    /// Any pattern not known to this version of the model.
+    #[serde(other)]
+    Unknown,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -218,6 +368,10 @@ pub enum DayOfWeek {
     Thursday,
     Friday,
     Saturday,
+
+    /// Any value not known to this version of the model.
+    #[serde(other)]
+    Unknown,
 }
 
 /// Describes a date range over which a recurring `TodoTask` repeats.
@@ -228,6 +382,7 @@ pub enum DayOfWeek {
 #[serde(tag = "type")]
 pub enum RecurrenceRange {
     /// Event repeats on all the days that fit the corresponding recurrence pattern between the `start_date` and `end_date` inclusive.
+    #[serde(rename_all = "camelCase")]
     EndDate { 
         /// The date to start applying the recurrence pattern. 
         /// The first occurrence of the meeting may be this date or later, 
@@ -246,6 +401,7 @@ pub enum RecurrenceRange {
     },
 
     /// Event repeats on all the days that fit the corresponding recurrence pattern beginning on the `start_date`.
+    #[serde(rename_all = "camelCase")]
     NoEnd { 
         /// The date to start applying the recurrence pattern. 
         /// The first occurrence of the meeting may be this date or later, 
@@ -259,6 +415,7 @@ pub enum RecurrenceRange {
     },
 
     /// Event repeats for the `number_of_occurrences` based on the recurrence pattern beginning on the `start_date`.
+    #[serde(rename_all = "camelCase")]
     Numbered { 
         /// The date to start applying the recurrence pattern. 
         /// The first occurrence of the meeting may be this date or later, 
@@ -274,4 +431,8 @@ pub enum RecurrenceRange {
         /// If not specified, the time zone of the event is used.
         recurrence_time_zone: Option<String> 
     },
+
+    /// Any range not known to this version of the model.
+    #[serde(other)]
+    Unknown,
 }
\ No newline at end of file
@@ -259,7 +259,11 @@ pub struct User {
 pub enum AgeGroup {
     Minor,
     NotAdult,
-    Adult
+    Adult,
+
+    /// Any value not known to this version of the model.
+    #[serde(other)]
+    Unknown,
 }
 
 
@@ -268,7 +272,11 @@ pub enum AgeGroup {
 pub enum ConsentProvidedForMinor {
     Granted,
     Denied,
-    NotRequired
+    NotRequired,
+
+    /// Any value not known to this version of the model.
+    #[serde(other)]
+    Unknown,
 }
 
 #[derive(Deserialize, Debug, Clone)]
@@ -279,6 +287,10 @@ pub enum LegalAgeGroupClassification {
     MinorNoParentalConsentRequired, 
     NotAdult,
     Adult,
+
+    /// Any value not known to this version of the model.
+    #[serde(other)]
+    Unknown,
 }
 
 /// See: https://docs.microsoft.com/en-us/graph/api/resources/objectidentity?view=graph-rest-1.0
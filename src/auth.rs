@@ -0,0 +1,283 @@
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::thread;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::error::{Error, Result};
+
+/// The client id of the "Microsoft Graph Command Line Tools" public client application,
+/// which supports the device code flow for both personal and work accounts.
+pub const DEFAULT_CLIENT_ID: &str = "14d82eec-204b-4c2f-b7e8-296a70dab67e";
+
+/// The scopes requested when logging in. Writing tasks is needed to import an export.
+const SCOPES: &str = "Tasks.ReadWrite Notes.Read offline_access";
+
+/// Access tokens expiring within this many seconds are refreshed before use.
+const EXPIRY_MARGIN_SECS: u64 = 60;
+
+/// The application registration and tenant used to sign in.
+#[derive(Debug, Clone)]
+pub struct AuthConfig {
+    /// The root url of the identity platform, e.g. `https://login.microsoftonline.us` for the US Government cloud.
+    pub authority: String,
+
+    /// The client id of the application registration.
+    pub client_id: String,
+
+    /// The tenant to sign in to: `common`, `consumers`, `organizations` or a tenant id.
+    pub tenant: String,
+
+    /// The file in which the tokens are cached between runs.
+    pub cache_path: PathBuf,
+}
+
+impl AuthConfig {
+    fn endpoint(&self, path: &str) -> String {
+        format!("{}/{}/oauth2/v2.0/{}", self.authority.trim_end_matches('/'), self.tenant, path)
+    }
+}
+
+/// The tokens persisted to the token cache file.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct TokenCache {
+    /// The client id the tokens were issued to; refreshing requires the same client id.
+    pub client_id: String,
+
+    /// The tenant the tokens were issued by.
+    pub tenant: String,
+
+    pub access_token: String,
+
+    pub refresh_token: String,
+
+    /// When the access token expires, in seconds since the unix epoch.
+    pub expires_at: u64,
+}
+
+impl TokenCache {
+    /// Read the token cache from `path`.
+    pub fn load(path: &Path) -> Result<Self> {
+        Ok(serde_json::from_str(&fs::read_to_string(path)?)?)
+    }
+
+    /// Write the token cache to `path`, readable by the current user only as it holds a long-lived refresh token.
+    pub fn save(&self, path: &Path) -> Result<()> {
+        let mut options = fs::OpenOptions::new();
+        options.write(true).create(true).truncate(true);
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::{OpenOptionsExt, PermissionsExt};
+            options.mode(0o600);
+
+            // The mode only applies to new files; tighten a cache written by an earlier version as well.
+            if path.exists() {
+                fs::set_permissions(path, fs::Permissions::from_mode(0o600))?;
+            }
+        }
+
+        let mut file = options.open(path)?;
+        file.write_all(serde_json::to_string_pretty(self)?.as_bytes())?;
+        Ok(())
+    }
+
+    fn is_expired(&self) -> bool {
+        now() + EXPIRY_MARGIN_SECS >= self.expires_at
+    }
+}
+
+/// The response to a device authorization request.
+///
+/// See: https://docs.microsoft.com/en-us/azure/active-directory/develop/v2-oauth2-device-code
+#[derive(Deserialize, Debug)]
+struct DeviceCodeResponse {
+    device_code: String,
+    message: String,
+    expires_in: u64,
+    interval: u64,
+}
+
+/// A successful response from the token endpoint.
+#[derive(Deserialize, Debug)]
+struct TokenResponse {
+    access_token: String,
+    refresh_token: Option<String>,
+    expires_in: u64,
+}
+
+/// An error response from the token endpoint.
+#[derive(Deserialize, Debug)]
+struct TokenErrorResponse {
+    error: String,
+    error_description: Option<String>,
+}
+
+impl From<TokenErrorResponse> for Error {
+    fn from(e: TokenErrorResponse) -> Error {
+        Error::AuthError(format!("{}: {}", e.error, e.error_description.unwrap_or_default()))
+    }
+}
+
+/// Sign in with the device code flow, printing the instructions for the user,
+/// and write the received tokens into the token cache.
+pub fn login(config: &AuthConfig) -> Result<TokenCache> {
+    let client = reqwest::blocking::Client::new();
+
+    let device_code: DeviceCodeResponse = client
+        .post(config.endpoint("devicecode"))
+        .form(&[("client_id", config.client_id.as_str()), ("scope", SCOPES)])
+        .send()?
+        .error_for_status()?
+        .json()?;
+
+    println!("{}", device_code.message);
+
+    let deadline = now() + device_code.expires_in;
+    let mut interval = device_code.interval;
+    while now() < deadline {
+        thread::sleep(Duration::from_secs(interval));
+
+        let response = client
+            .post(config.endpoint("token"))
+            .form(&[
+                ("grant_type", "urn:ietf:params:oauth:grant-type:device_code"),
+                ("client_id", config.client_id.as_str()),
+                ("device_code", device_code.device_code.as_str()),
+            ])
+            .send()?;
+
+        if response.status().is_success() {
+            let cache = to_cache(config, response.json()?, None)?;
+            cache.save(&config.cache_path)?;
+            return Ok(cache);
+        }
+
+        let error: TokenErrorResponse = response.json()?;
+        match error.error.as_str() {
+            "authorization_pending" => {}
+            "slow_down" => interval += 5,
+            _ => return Err(error.into()),
+        }
+    }
+
+    Err(Error::AuthError("the device code expired before sign in completed".to_string()))
+}
+
+/// Exchange the refresh token in `cache` for a new access token.
+fn refresh(config: &AuthConfig, cache: &TokenCache) -> Result<TokenCache> {
+    let response = reqwest::blocking::Client::new()
+        .post(config.endpoint("token"))
+        .form(&[
+            ("grant_type", "refresh_token"),
+            ("client_id", cache.client_id.as_str()),
+            ("refresh_token", cache.refresh_token.as_str()),
+            ("scope", SCOPES),
+        ])
+        .send()?;
+
+    if !response.status().is_success() {
+        let error: TokenErrorResponse = response.json()?;
+        return Err(error.into());
+    }
+
+    to_cache(config, response.json()?, Some(&cache.refresh_token))
+}
+
+fn to_cache(config: &AuthConfig, token: TokenResponse, previous_refresh_token: Option<&str>) -> Result<TokenCache> {
+    let refresh_token = token.refresh_token
+        .or_else(|| previous_refresh_token.map(|t| t.to_string()))
+        .ok_or_else(|| Error::AuthError("no refresh token was issued; is offline_access granted?".to_string()))?;
+
+    Ok(TokenCache {
+        client_id: config.client_id.clone(),
+        tenant: config.tenant.clone(),
+        access_token: token.access_token,
+        refresh_token,
+        expires_at: now() + token.expires_in,
+    })
+}
+
+fn now() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0)
+}
+
+/// A source of access tokens for Graph requests, which may be shared between download workers.
+pub trait TokenProvider: Send + Sync {
+    /// Returns an access token which is valid for a while yet.
+    fn access_token(&self) -> Result<String>;
+
+    /// Returns a new access token, after the current one was rejected by Graph.
+    fn refresh_access_token(&self) -> Result<String>;
+}
+
+/// An access token given on the command line, which can not be refreshed.
+pub struct StaticToken(pub String);
+
+impl TokenProvider for StaticToken {
+    fn access_token(&self) -> Result<String> {
+        Ok(self.0.clone())
+    }
+
+    fn refresh_access_token(&self) -> Result<String> {
+        Err(Error::AuthError("the access token was rejected and can not be refreshed; log in instead of passing --token".to_string()))
+    }
+}
+
+/// Returns the token provider for Graph requests: the given access `token` if there is one, otherwise
+/// the login cached for `config`, logging in first when there is none.
+pub fn token_provider(token: Option<&str>, config: AuthConfig) -> Result<Box<dyn TokenProvider>> {
+    if let Some(token) = token {
+        return Ok(Box::new(StaticToken(token.to_string())));
+    }
+
+    if !config.cache_path.exists() {
+        login(&config)?;
+    }
+
+    Ok(Box::new(CachedLogin::load(config)?))
+}
+
+/// The tokens from the token cache, refreshed whenever the access token is about to expire or is rejected.
+pub struct CachedLogin {
+    config: AuthConfig,
+    cache: Mutex<TokenCache>,
+}
+
+impl CachedLogin {
+    /// Use the tokens cached by an earlier `login`.
+    pub fn load(config: AuthConfig) -> Result<Self> {
+        let cache = TokenCache::load(&config.cache_path)?;
+
+        // Refreshing must use the application the tokens were issued to.
+        let config = AuthConfig {
+            client_id: cache.client_id.clone(),
+            tenant: cache.tenant.clone(),
+            ..config
+        };
+
+        Ok(CachedLogin { config, cache: Mutex::new(cache) })
+    }
+
+    fn refresh_locked(&self, cache: &mut TokenCache) -> Result<String> {
+        *cache = refresh(&self.config, cache)?;
+        cache.save(&self.config.cache_path)?;
+        Ok(cache.access_token.clone())
+    }
+}
+
+impl TokenProvider for CachedLogin {
+    fn access_token(&self) -> Result<String> {
+        let mut cache = self.cache.lock().unwrap();
+        if cache.is_expired() {
+            return self.refresh_locked(&mut cache);
+        }
+        Ok(cache.access_token.clone())
+    }
+
+    fn refresh_access_token(&self) -> Result<String> {
+        let mut cache = self.cache.lock().unwrap();
+        self.refresh_locked(&mut cache)
+    }
+}
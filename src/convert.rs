@@ -0,0 +1,61 @@
+use std::fs;
+use std::path::Path;
+
+use crate::error::Result;
+use crate::markdown;
+use crate::onenote;
+
+/// The formats downloaded OneNote pages can be converted into.
+#[derive(clap::ValueEnum, Clone, Copy, Debug)]
+pub enum ConvertFormat {
+    Markdown,
+}
+
+/// Convert every `.html` page in `input` into `format`, writing the results into `output`
+/// under the same file name with the format's extension.
+///
+/// The images and files downloaded with each page are copied along, so that the links to them keep working.
+pub fn convert(input: &Path, output: &Path, format: ConvertFormat) -> Result<()> {
+    fs::create_dir_all(output)?;
+
+    let mut converted = 0;
+    for entry in fs::read_dir(input)? {
+        let path = entry?.path();
+        if path.extension().is_none_or(|extension| extension != "html") {
+            continue;
+        }
+
+        let html = fs::read_to_string(&path)?;
+        let (content, extension) = match format {
+            ConvertFormat::Markdown => (markdown::convert_page(&html), "md"),
+        };
+
+        let file_name = path.with_extension(extension);
+        fs::write(output.join(file_name.file_name().unwrap_or_default()), content)?;
+        if let Some(stem) = path.file_stem() {
+            copy_resources(input, output, &onenote::resources_dir_name(&stem.to_string_lossy()))?;
+        }
+        converted += 1;
+    }
+
+    println!("{} pages converted", converted);
+    Ok(())
+}
+
+/// Copy the resources directory `dir_name` of a page from `input` into `output`, if the page has one.
+fn copy_resources(input: &Path, output: &Path, dir_name: &str) -> Result<()> {
+    let dir = input.join(dir_name);
+    if !dir.is_dir() {
+        return Ok(());
+    }
+
+    let target = output.join(dir_name);
+    fs::create_dir_all(&target)?;
+    for entry in fs::read_dir(&dir)? {
+        let entry = entry?;
+        if entry.file_type()?.is_file() {
+            fs::copy(entry.path(), target.join(entry.file_name()))?;
+        }
+    }
+    Ok(())
+}
@@ -3,12 +3,21 @@ use std::convert::From;
 pub type Result<T> = ::std::result::Result<T, Error>;
 
 #[derive(Debug, Display)]
+#[allow(clippy::enum_variant_names)]
 pub enum Error {
     IOError(::std::io::Error),
 
     ReqwestError(::reqwest::Error),
 
     ParseIntError(::std::num::ParseIntError),
+
+    SerdeJsonError(::serde_json::Error),
+
+    RegexError(::regex::Error),
+
+    AuthError(String),
+
+    QuakeError(String),
 }
 
 impl ::std::error::Error for Error {
@@ -17,6 +26,9 @@ impl ::std::error::Error for Error {
             Self::IOError(e) => Some(e),
             Self::ReqwestError(e) => Some(e),
             Self::ParseIntError(e) => Some(e),
+            Self::SerdeJsonError(e) => Some(e),
+            Self::RegexError(e) => Some(e),
+            Self::AuthError(_) | Self::QuakeError(_) => None,
         }
     }
 }
@@ -39,4 +51,14 @@ impl From<::std::num::ParseIntError> for Error {
     }
 }
 
+impl From<::serde_json::Error> for Error {
+    fn from(e: ::serde_json::Error) -> Error {
+        Error::SerdeJsonError(e)
+    }
+}
 
+impl From<::regex::Error> for Error {
+    fn from(e: ::regex::Error) -> Error {
+        Error::RegexError(e)
+    }
+}
@@ -0,0 +1,89 @@
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+use crate::error::Result;
+
+/// The longest file name, in bytes, produced by `safe_file_name`; most file systems allow 255.
+const MAX_FILE_NAME_LEN: usize = 200;
+
+/// Names which Windows reserves for devices, with or without an extension.
+const RESERVED_NAMES: [&str; 22] = [
+    "CON", "PRN", "AUX", "NUL",
+    "COM1", "COM2", "COM3", "COM4", "COM5", "COM6", "COM7", "COM8", "COM9",
+    "LPT1", "LPT2", "LPT3", "LPT4", "LPT5", "LPT6", "LPT7", "LPT8", "LPT9",
+];
+
+/// Turn `name`, e.g. a task title or an attachment name, into a file name which is valid on
+/// Windows, macOS and Linux: path separators and other reserved characters are replaced with `_`,
+/// and the name is shortened to `MAX_FILE_NAME_LEN` bytes.
+pub fn safe_file_name(name: &str) -> String {
+    let mut safe: String = name.chars()
+        .map(|c| match c {
+            '<' | '>' | ':' | '"' | '/' | '\\' | '|' | '?' | '*' => '_',
+            c if c.is_control() => '_',
+            c => c,
+        })
+        .collect();
+
+    if safe.len() > MAX_FILE_NAME_LEN {
+        let mut end = MAX_FILE_NAME_LEN;
+        while !safe.is_char_boundary(end) {
+            end -= 1;
+        }
+        safe.truncate(end);
+    }
+
+    // Windows drops trailing dots and spaces, which could make two names collide.
+    let safe = safe.trim().trim_end_matches('.').to_string();
+
+    let stem = safe.split('.').next().unwrap_or_default().to_uppercase();
+    if safe.is_empty() || safe == "." || RESERVED_NAMES.contains(&stem.as_str()) {
+        return format!("_{}", safe);
+    }
+    safe
+}
+
+/// The path of the safe file name for `name` in `dir`, suffixed with ` (2)`, ` (3)`, etc. when an
+/// earlier call already returned the same path. `used` holds the paths returned so far.
+pub fn unique_path(dir: &Path, name: &str, used: &mut HashSet<PathBuf>) -> PathBuf {
+    let name = safe_file_name(name);
+    let (stem, extension) = match name.rfind('.') {
+        Some(dot) if dot > 0 => name.split_at(dot),
+        _ => (name.as_str(), ""),
+    };
+
+    let mut path = dir.join(&name);
+    let mut n = 2;
+    while used.contains(&path) {
+        path = dir.join(format!("{} ({}){}", stem, n, extension));
+        n += 1;
+    }
+
+    used.insert(path.clone());
+    path
+}
+
+/// State kept between runs in a JSON file, such as the sync state or the download manifest.
+pub trait JsonFile: Serialize + DeserializeOwned + Default {
+    /// Read the state from `path`, or start from the default when there is no such file.
+    fn load(path: &Path) -> Result<Self> {
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        Ok(serde_json::from_str(&fs::read_to_string(path)?)?)
+    }
+
+    /// Write the state to `path`, through a `.part` file which replaces it once written, so that an
+    /// interrupted run leaves the earlier state behind rather than a truncated file.
+    fn save(&self, path: &Path) -> Result<()> {
+        let mut part = path.as_os_str().to_owned();
+        part.push(".part");
+        fs::write(&part, serde_json::to_string_pretty(self)?)?;
+        fs::rename(&part, path)?;
+        Ok(())
+    }
+}
@@ -0,0 +1,99 @@
+use regex::Regex;
+
+use crate::error::Result;
+
+/// A list of include and exclude patterns that names are matched against.
+///
+/// Patterns are globs (`*` and `?` wildcards) matched against the whole name, unless
+/// they are prefixed with `re:`, in which case the rest of the pattern is a regular expression.
+#[derive(Debug, Default)]
+pub struct NameFilter {
+    /// When not empty, a name must match at least one of these patterns.
+    include: Vec<Regex>,
+
+    /// A name matching any of these patterns is rejected.
+    exclude: Vec<Regex>,
+}
+
+impl NameFilter {
+    /// Create a new filter from the given `include` and `exclude` patterns.
+    pub fn new(include: &[String], exclude: &[String]) -> Result<Self> {
+        Ok(Self {
+            include: include.iter().map(|p| compile(p)).collect::<Result<_>>()?,
+            exclude: exclude.iter().map(|p| compile(p)).collect::<Result<_>>()?,
+        })
+    }
+
+    /// Were no patterns given, so that every name passes?
+    pub fn is_empty(&self) -> bool {
+        self.include.is_empty() && self.exclude.is_empty()
+    }
+
+    /// Does `name` pass the filter?
+    pub fn matches(&self, name: &str) -> bool {
+        self.includes(name) && !self.excludes(name)
+    }
+
+    /// Does `name` match one of the include patterns, or were none given?
+    pub fn includes(&self, name: &str) -> bool {
+        self.include.is_empty() || self.include.iter().any(|re| re.is_match(name))
+    }
+
+    /// Does `name` match one of the exclude patterns?
+    pub fn excludes(&self, name: &str) -> bool {
+        self.exclude.iter().any(|re| re.is_match(name))
+    }
+
+    /// Does a possibly missing `name` pass the filter?
+    /// A missing name only passes when no include patterns were given.
+    pub fn matches_optional(&self, name: Option<&str>) -> bool {
+        match name {
+            Some(name) => self.matches(name),
+            None => self.include.is_empty(),
+        }
+    }
+
+    /// Does a section nested in the groups `names`, outermost first, pass the filter? As in the notebook tree,
+    /// none of the groups may be excluded and one of them must be included, so that an included group includes
+    /// the groups nested in it. Sections outside of any group pass like a missing name.
+    pub fn matches_nested(&self, names: &[String]) -> bool {
+        if names.is_empty() {
+            return self.matches_optional(None);
+        }
+        !names.iter().any(|name| self.excludes(name)) && names.iter().any(|name| self.includes(name))
+    }
+}
+
+/// The filters applied to the notebooks, section groups and sections of a OneNote export.
+#[derive(Debug, Default)]
+pub struct OnenoteFilter {
+    pub notebooks: NameFilter,
+    pub section_groups: NameFilter,
+    pub sections: NameFilter,
+}
+
+impl OnenoteFilter {
+    /// Does every notebook, section group and section pass the filter?
+    pub fn is_empty(&self) -> bool {
+        self.notebooks.is_empty() && self.section_groups.is_empty() && self.sections.is_empty()
+    }
+}
+
+/// Compile a glob or `re:` prefixed regex `pattern` into a `Regex`.
+fn compile(pattern: &str) -> Result<Regex> {
+    if let Some(re) = pattern.strip_prefix("re:") {
+        return Ok(Regex::new(re)?);
+    }
+
+    let mut re = String::from("^");
+    for c in pattern.chars() {
+        match c {
+            '*' => re.push_str(".*"),
+            '?' => re.push('.'),
+            c => re.push_str(&regex::escape(&c.to_string())),
+        }
+    }
+    re.push('$');
+
+    Ok(Regex::new(&re)?)
+}
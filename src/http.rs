@@ -0,0 +1,253 @@
+use std::sync::Mutex;
+use std::thread;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use reqwest::blocking::{RequestBuilder, Response};
+use reqwest::header::{HeaderMap, RETRY_AFTER};
+use reqwest::{Method, StatusCode};
+use quake_microsoft_todo::paging::Pager;
+use quake_microsoft_todo::Collection;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+use crate::auth::TokenProvider;
+use crate::error::Result;
+
+/// How throttled and failing requests are retried.
+#[derive(Debug, Clone, Copy)]
+pub struct RetryPolicy {
+    /// The maximum number of attempts per request, including the first one.
+    pub max_attempts: u32,
+
+    /// The delay before the first retry, doubled on every further retry.
+    pub base_delay: Duration,
+
+    /// The longest delay between two attempts, also when the server asks for a longer one.
+    pub max_delay: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        RetryPolicy {
+            max_attempts: 5,
+            base_delay: Duration::from_secs(1),
+            max_delay: Duration::from_secs(60),
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Should a response with the given `status` to a `method` request be retried?
+    /// Graph answers `429` when throttling and `503` when overloaded; gateways may answer `502` or `504`.
+    ///
+    /// Only a throttled `POST` is retried: any other failure may come after Graph created the resource,
+    /// and sending it again would create it twice.
+    pub fn is_retryable(method: &Method, status: u16) -> bool {
+        match *method {
+            Method::POST => status == 429,
+            _ => matches!(status, 429 | 502 | 503 | 504),
+        }
+    }
+
+    /// Should a `method` request which failed with `error` be retried? Requests which couldn't connect were
+    /// never processed; those which timed out may have been, so only the ones which can be repeated are retried.
+    pub fn is_retryable_error(method: &Method, error: &reqwest::Error) -> bool {
+        error.is_connect() || (error.is_timeout() && *method != Method::POST)
+    }
+
+    /// The delay before the given retry `attempt` (starting at 1): the server's `retry_after` when it
+    /// sent one, otherwise an exponential backoff with up to 50% random jitter; at most `max_delay` either way.
+    pub fn delay(&self, attempt: u32, retry_after: Option<Duration>) -> Duration {
+        if let Some(retry_after) = retry_after {
+            return retry_after.min(self.max_delay);
+        }
+
+        let backoff = self.base_delay
+            .checked_mul(1 << attempt.saturating_sub(1).min(16))
+            .unwrap_or(self.max_delay)
+            .min(self.max_delay);
+
+        backoff + backoff.mul_f64(jitter() * 0.5)
+    }
+}
+
+/// A pseudo random number between 0 and 1, good enough to spread retries apart.
+fn jitter() -> f64 {
+    let nanos = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.subsec_nanos()).unwrap_or(0);
+    f64::from(nanos % 1000) / 1000.0
+}
+
+/// A delay from which on retrying says that every request is paused, not only the one retried.
+const LONG_DELAY: Duration = Duration::from_secs(10);
+
+/// The `Retry-After` header of a response, which Graph sends as a number of seconds; other servers,
+/// e.g. a gateway, may send the HTTP date to retry at instead.
+fn retry_after(headers: &HeaderMap) -> Option<Duration> {
+    let value = headers.get(RETRY_AFTER)?.to_str().ok()?.trim();
+    if let Ok(seconds) = value.parse() {
+        return Some(Duration::from_secs(seconds));
+    }
+
+    // Otherwise an HTTP date, e.g. `Wed, 21 Oct 2015 07:28:00 GMT`, which may already have passed.
+    let date = httpdate::parse_http_date(value).ok()?;
+    Some(date.duration_since(SystemTime::now()).unwrap_or_default())
+}
+
+/// Where the Graph API is served from.
+#[derive(Debug, Clone)]
+pub struct GraphEndpoint {
+    /// The root of the service, e.g. `https://graph.microsoft.com`, `https://graph.microsoft.us`
+    /// for a national cloud, or a local stub server.
+    pub base_url: String,
+
+    /// The API version: `v1.0` or `beta`.
+    pub version: String,
+}
+
+impl Default for GraphEndpoint {
+    fn default() -> Self {
+        GraphEndpoint {
+            base_url: "https://graph.microsoft.com".to_string(),
+            version: "v1.0".to_string(),
+        }
+    }
+}
+
+impl GraphEndpoint {
+    /// The full url of the API `path`, e.g. `/me/todo/lists`.
+    pub fn url(&self, path: &str) -> String {
+        format!("{}/{}{}", self.base_url.trim_end_matches('/'), self.version, path)
+    }
+}
+
+/// A `reqwest` client which authorizes every Graph request with a token from a `TokenProvider`,
+/// and retries the requests which were throttled or failed along the way.
+///
+/// The client may be shared between threads; once Graph throttles one of them, all of them
+/// hold off sending further requests until the throttling delay has passed.
+pub struct GraphClient<'a> {
+    http: reqwest::blocking::Client,
+
+    tokens: &'a dyn TokenProvider,
+
+    endpoint: GraphEndpoint,
+
+    retry: RetryPolicy,
+
+    /// No request is sent before this instant.
+    paused_until: Mutex<Instant>,
+}
+
+impl<'a> GraphClient<'a> {
+    pub fn new(tokens: &'a dyn TokenProvider, endpoint: GraphEndpoint, retry: RetryPolicy) -> Self {
+        Self {
+            http: reqwest::blocking::Client::new(),
+            tokens,
+            endpoint,
+            retry,
+            paused_until: Mutex::new(Instant::now()),
+        }
+    }
+
+    /// The full url of the API `path` on the client's endpoint.
+    pub fn url(&self, path: &str) -> String {
+        self.endpoint.url(path)
+    }
+
+    /// Is `url` on the client's endpoint, so that it may be sent the access token?
+    pub fn is_graph_url(&self, url: &str) -> bool {
+        url.starts_with(&format!("{}/", self.endpoint.base_url.trim_end_matches('/')))
+    }
+
+    /// Send the request built by `request`.
+    ///
+    /// When the access token is rejected, it is refreshed and the request is sent once more.
+    /// Throttled (`429`), unavailable (`503`) and timed out requests are retried after the server's
+    /// `Retry-After`, or an exponential backoff, until the retry policy's `max_attempts` is reached;
+    /// `POST`s only when throttled or not sent at all, see `RetryPolicy::is_retryable`.
+    pub fn send<F>(&self, request: F) -> Result<Response>
+        where F: Fn(&reqwest::blocking::Client) -> RequestBuilder
+    {
+        let mut attempt = 1;
+        let mut refreshed = false;
+        let mut token = self.tokens.access_token()?;
+
+        loop {
+            self.wait_for_pause();
+
+            let built = request(&self.http)
+                .bearer_auth(&token)
+                .build()?;
+            let method = built.method().clone();
+            let result = self.http.execute(built);
+
+            let retry_after = match &result {
+                Ok(response) if response.status() == StatusCode::UNAUTHORIZED && !refreshed => {
+                    token = self.tokens.refresh_access_token()?;
+                    refreshed = true;
+                    continue;
+                }
+                Ok(response) if RetryPolicy::is_retryable(&method, response.status().as_u16()) => retry_after(response.headers()),
+                Err(e) if RetryPolicy::is_retryable_error(&method, e) => None,
+                _ => return Ok(result?),
+            };
+
+            if attempt >= self.retry.max_attempts {
+                return Ok(result?);
+            }
+
+            let delay = self.retry.delay(attempt, retry_after);
+            let reason = match &result {
+                Ok(response) => format!("request failed with {}", response.status()),
+                Err(e) => format!("request failed ({})", e),
+            };
+            let wait = if delay >= LONG_DELAY {
+                format!("pausing every request for {:.1?}", delay)
+            } else {
+                format!("retrying in {:.1?}", delay)
+            };
+            println!("{}, {} (attempt {} of {})", reason, wait, attempt + 1, self.retry.max_attempts);
+            if let Some(asked) = retry_after.filter(|&asked| asked > delay) {
+                println!("the server asked to wait {:.1?}, longer than --max-delay allows; retrying earlier", asked);
+            }
+            self.pause(delay);
+
+            attempt += 1;
+            token = self.tokens.access_token()?;
+        }
+    }
+
+    /// Hold off every request for at least `delay`.
+    fn pause(&self, delay: Duration) {
+        let mut paused_until = self.paused_until.lock().unwrap();
+        *paused_until = (*paused_until).max(Instant::now() + delay);
+    }
+
+    /// Sleep until the current pause, if any, has passed.
+    fn wait_for_pause(&self) {
+        let paused_until = *self.paused_until.lock().unwrap();
+        let now = Instant::now();
+        if paused_until > now {
+            thread::sleep(paused_until - now);
+        }
+    }
+
+    /// `GET` the JSON resource at `url`.
+    pub fn get_json<T: DeserializeOwned>(&self, url: &str) -> Result<T> {
+        Ok(self.send(|http| http.get(url))?
+            .error_for_status()?
+            .json()?)
+    }
+
+    /// `POST` the JSON `body` to `url`, returning the created resource.
+    pub fn post_json<B: Serialize, T: DeserializeOwned>(&self, url: &str, body: &B) -> Result<T> {
+        Ok(self.send(|http| http.post(url).json(body))?
+            .error_for_status()?
+            .json()?)
+    }
+
+    /// Iterate over every item of the paged collection at `url`, following its next links.
+    pub fn pages<T: DeserializeOwned>(&self, url: &str) -> Pager<T, impl FnMut(&str) -> Result<Collection<T>> + '_> {
+        Pager::new(url, move |url: &str| self.get_json(url))
+    }
+}
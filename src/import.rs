@@ -0,0 +1,242 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+
+use quake_microsoft_todo::tasks::{
+    BodyType, ChecklistItem, DayOfWeek, Importance, ItemBody, PatternedRecurrence, RecurrencePattern, RecurrenceRange,
+    TaskStatus, TodoTask, TodoTaskList, WellknownListName,
+};
+use quake_microsoft_todo::{DateTimeOffset, DateTimeTimeZone};
+
+use crate::error::Result;
+use crate::files::JsonFile;
+use crate::http::GraphClient;
+use crate::{todo, OutputList};
+
+/// The ids of the lists, tasks and checklist items created by an import, keyed by their id in the export.
+///
+/// The map is saved after every created item, so that an interrupted import can be run again
+/// without creating anything twice.
+#[derive(Serialize, Deserialize, Debug, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct IdMap {
+    pub lists: BTreeMap<String, String>,
+    pub tasks: BTreeMap<String, String>,
+    pub checklist_items: BTreeMap<String, String>,
+}
+
+impl JsonFile for IdMap {}
+
+/// The id of a created resource.
+#[derive(Deserialize, Debug)]
+struct Created {
+    id: String,
+}
+
+/// The writable properties of a `TodoTaskList`.
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+struct NewList<'a> {
+    display_name: &'a str,
+}
+
+/// The writable properties of a `TodoTask`. Values which the model didn't recognize when exporting are left out,
+/// or replaced with their default, as Graph rejects them: a body of an unknown type is sent as text, unknown days are
+/// dropped from a recurrence, and a recurrence which is left without a day, pattern or range is not sent.
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+struct NewTask<'a> {
+    title: &'a str,
+
+    body: ItemBody,
+
+    #[serde(skip_serializing_if = "<[_]>::is_empty")]
+    categories: &'a [String],
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    importance: Option<&'a Importance>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    status: Option<&'a TaskStatus>,
+
+    is_reminder_on: bool,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    reminder_date_time: Option<&'a DateTimeTimeZone>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    start_date_time: Option<&'a DateTimeTimeZone>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    due_date_time: Option<&'a DateTimeTimeZone>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    completed_date_time: Option<&'a DateTimeTimeZone>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    recurrence: Option<PatternedRecurrence>,
+
+    /// Graph takes the original timestamps on creation, rather than the time of the import.
+    created_date_time: &'a DateTimeOffset,
+
+    last_modified_date_time: &'a DateTimeOffset,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    body_last_modified_date_time: Option<&'a DateTimeOffset>,
+}
+
+impl<'a> From<&'a TodoTask> for NewTask<'a> {
+    fn from(task: &'a TodoTask) -> Self {
+        NewTask {
+            title: &task.title,
+            body: ItemBody {
+                content: task.body.content.clone(),
+                content_type: match task.body.content_type {
+                    BodyType::Unknown => BodyType::Text,
+                    ref content_type => content_type.clone(),
+                },
+            },
+            categories: &task.categories,
+            importance: Some(&task.importance).filter(|i| !matches!(i, Importance::Unknown)),
+            status: Some(&task.status).filter(|s| !matches!(s, TaskStatus::Unknown)),
+            is_reminder_on: task.is_reminder_on,
+            reminder_date_time: task.reminder_date_time.as_ref(),
+            start_date_time: task.start_date_time.as_ref(),
+            due_date_time: task.due_date_time.as_ref(),
+            completed_date_time: task.completed_date_time.as_ref(),
+            recurrence: task.recurrence.as_ref().and_then(writable_recurrence),
+            created_date_time: &task.created_date_time,
+            last_modified_date_time: &task.last_modified_date_time,
+            body_last_modified_date_time: task.body_last_modified_date_time.as_ref(),
+        }
+    }
+}
+
+/// `recurrence` without the values the model didn't recognize, or nothing if it can't do without them.
+fn writable_recurrence(recurrence: &PatternedRecurrence) -> Option<PatternedRecurrence> {
+    let known_days = |days: &[DayOfWeek]| -> Option<Vec<DayOfWeek>> {
+        let days: Vec<DayOfWeek> = days.iter().filter(|day| !matches!(day, DayOfWeek::Unknown)).cloned().collect();
+        Some(days).filter(|days| !days.is_empty())
+    };
+
+    let pattern = match &recurrence.pattern {
+        RecurrencePattern::Unknown => return None,
+        RecurrencePattern::Weekly { interval, days_of_week, first_day_of_week } => RecurrencePattern::Weekly {
+            interval: *interval,
+            days_of_week: known_days(days_of_week)?,
+            first_day_of_week: match first_day_of_week {
+                DayOfWeek::Unknown => DayOfWeek::Sunday,
+                day => day.clone(),
+            },
+        },
+        RecurrencePattern::RelativeMonthly { interval, days_of_week } => RecurrencePattern::RelativeMonthly {
+            interval: *interval,
+            days_of_week: known_days(days_of_week)?,
+        },
+        RecurrencePattern::RelativeYearly { interval, days_of_week, month } => RecurrencePattern::RelativeYearly {
+            interval: *interval,
+            days_of_week: known_days(days_of_week)?,
+            month: *month,
+        },
+        pattern => pattern.clone(),
+    };
+    if let RecurrenceRange::Unknown = recurrence.range {
+        return None;
+    }
+
+    Some(PatternedRecurrence { pattern, range: recurrence.range.clone() })
+}
+
+/// The writable properties of a `ChecklistItem`.
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+struct NewChecklistItem<'a> {
+    display_name: &'a str,
+
+    is_checked: bool,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    checked_date_time: Option<&'a DateTimeOffset>,
+
+    created_date_time: &'a DateTimeOffset,
+}
+
+impl<'a> From<&'a ChecklistItem> for NewChecklistItem<'a> {
+    fn from(item: &'a ChecklistItem) -> Self {
+        NewChecklistItem {
+            display_name: &item.display_name,
+            is_checked: item.is_checked,
+            checked_date_time: item.checked_date_time.as_ref(),
+            created_date_time: &item.created_date_time,
+        }
+    }
+}
+
+/// Recreate the lists, tasks and checklist items of the export at `input` in the signed-in account.
+///
+/// The exported default list is imported into the account's own default list; every other list
+/// is created anew. The ids of everything created are kept in the id map at `id_map_path`, and
+/// anything already in that map is skipped.
+pub fn import_todos(client: &GraphClient, input: &Path, id_map_path: &Path) -> Result<()> {
+    let lists: Vec<OutputList> = serde_json::from_str(&fs::read_to_string(input)?)?;
+    import_lists(client, &lists, id_map_path)
+}
+
+/// Copy every list, task and checklist item of the `source` account into the `target` account.
+///
+/// The source's default list goes into the target's default list, and the ids created in the target
+/// are kept in the id map at `id_map_path`, like `import_todos`.
+pub fn migrate_todos(source: &GraphClient, target: &GraphClient, id_map_path: &Path) -> Result<()> {
+    let lists = todo::fetch_lists(source, todo::TaskDetails::Checklist)?;
+    import_lists(target, &lists, id_map_path)
+}
+
+/// Recreate `lists` in the signed-in account, like `import_todos`.
+pub fn import_lists(client: &GraphClient, lists: &[OutputList], id_map_path: &Path) -> Result<()> {
+    let mut ids = IdMap::load(id_map_path)?;
+
+    let lists_url = client.url("/me/todo/lists");
+    let default_list = client.pages::<TodoTaskList>(&lists_url)
+        .collect::<Result<Vec<_>>>()?
+        .into_iter()
+        .find(|list| matches!(list.wellknown_list_name, WellknownListName::DefaultList))
+        .map(|list| list.id);
+
+    for list in lists {
+        let list_id = match (ids.lists.get(&list.id), &list.wellknown_list_name, &default_list) {
+            (Some(id), _, _) => id.clone(),
+            (None, WellknownListName::DefaultList, Some(id)) => id.clone(),
+            (None, _, _) => client.post_json::<_, Created>(&lists_url, &NewList { display_name: &list.display_name })?.id,
+        };
+        ids.lists.insert(list.id.clone(), list_id.clone());
+        ids.save(id_map_path)?;
+
+        println!("list: {}", list.display_name);
+
+        let tasks_url = client.url(&format!("/me/todo/lists/{}/tasks", list_id));
+        for task in &list.children {
+            let task_id = match ids.tasks.get(&task.id) {
+                Some(id) => id.clone(),
+                None => {
+                    let created: Created = client.post_json(&tasks_url, &NewTask::from(task))?;
+                    ids.tasks.insert(task.id.clone(), created.id.clone());
+                    ids.save(id_map_path)?;
+                    created.id
+                }
+            };
+
+            let checklist_url = format!("{}/{}/checklistItems", tasks_url, task_id);
+            for item in &task.checklist_items {
+                if ids.checklist_items.contains_key(&item.id) {
+                    continue;
+                }
+
+                let created: Created = client.post_json(&checklist_url, &NewChecklistItem::from(item))?;
+                ids.checklist_items.insert(item.id.clone(), created.id);
+                ids.save(id_map_path)?;
+            }
+        }
+    }
+
+    Ok(())
+}
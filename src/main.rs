@@ -3,141 +3,366 @@ extern crate serde;
 #[macro_use]
 extern crate derive_more;
 
-use std::{fs, io};
-use std::collections::HashMap;
-use std::ffi::OsString;
 use std::path::PathBuf;
-use graph_http::{BlockingDownloadError, BlockingHttpClient};
-use graph_http::serde_json::Map;
-use graph_http::traits::ODataLink;
-use graph_rs_sdk::client::Graph;
-use graph_rs_sdk::prelude::GraphResponse;
-use graph_rs_sdk::serde_json::Value;
-use serde::de::DeserializeOwned;
+use std::time::Duration;
+use clap::{Args, Parser, Subcommand};
 
+mod auth;
+mod convert;
 mod error;
+mod files;
+mod filter;
+mod http;
+mod import;
+mod manifest;
+mod markdown;
 mod onenote;
+mod progress;
+mod quake;
+mod sync;
 mod todo;
+mod vault;
 
 use error::Result;
-use quake_microsoft_todo::Collection;
 use quake_microsoft_todo::tasks::{TodoTask, WellknownListName};
-use crate::onenote::download_page;
-
-const GRAPH_BASE_URI: &str = "https://graph.microsoft.com/beta";
-
-/// A hacky fucking struct for reading from a paged `Collection`.
-struct CollectionReader<'a, T> where T: DeserializeOwned + Clone {
-    /// The `reqwest` client from which to read the next links (pages) in the collection.
-    client: &'a reqwest::blocking::Client,
-
-    /// The user's OAuth access `token`.
-    token: &'a str,
-
-    /// The `Collection` to read.
-    collection: Option<Collection<T>>,
-
-    /// The full list of `items` which have been read from the `Collection` so far.
-    items: Vec<T>,
-
-    /// When iterating, the current index in the `items` vec.
-    iter_index: usize,
+use crate::auth::{AuthConfig, TokenProvider};
+use crate::http::{GraphClient, GraphEndpoint, RetryPolicy};
+use crate::convert::ConvertFormat;
+use crate::filter::{NameFilter, OnenoteFilter};
+use crate::onenote::{PageQuery, RemovedPages};
+use crate::todo::ExportFormat;
+
+/// Export Microsoft To Do tasks and OneNote notebooks through the Microsoft Graph API.
+#[derive(Parser, Debug)]
+#[command(name = "microsoft-todo-export", version)]
+struct Cli {
+    #[command(flatten)]
+    auth: AuthArgs,
+
+    /// The root url of the Graph service, e.g. `https://graph.microsoft.us` for the US Government cloud.
+    #[arg(long, env = "MS_GRAPH_URL", global = true, default_value_t = GraphEndpoint::default().base_url)]
+    graph_url: String,
+
+    /// The Graph API version: `v1.0` or `beta`.
+    #[arg(long, env = "MS_GRAPH_VERSION", global = true, default_value_t = GraphEndpoint::default().version)]
+    api_version: String,
+
+    /// The maximum number of attempts for a request which is throttled or fails.
+    #[arg(long, global = true, default_value_t = RetryPolicy::default().max_attempts)]
+    max_attempts: u32,
+
+    /// The longest wait in seconds between two attempts, also when Graph asks to wait longer.
+    #[arg(long, global = true, default_value_t = RetryPolicy::default().max_delay.as_secs())]
+    max_delay: u64,
+
+    #[command(subcommand)]
+    command: Command,
 }
 
-impl<'a, T: DeserializeOwned + Clone> CollectionReader<'a, T> {
-    /// Create a new collection reader, given the `client` and the access `token`.
-    pub fn new(client: &'a reqwest::blocking::Client, token: &'a str) -> Self {
-        Self {
-            client,
-            token,
-            collection: None,
-            items: Vec::new(),
-            iter_index: 0,
-        }
-    }
-
-    /// First action:
-    /// Fetch the requested collection from the given `url`.
-    pub fn fetch<S: AsRef<str>>(&mut self, url: S) -> Result<usize> {
-        self.fetch_inner(url)
-    }
-
-    /// Fetch the requested collection from the given `url`.
-    /// Append the received collection items into the `items` property.
-    fn fetch_inner<S: AsRef<str>>(&mut self, url: S) -> Result<usize> {
-        self.collection = Some(self.client
-            .get(url.as_ref())
-            .bearer_auth(self.token)
-            .send()?
-            .json()?);
+#[derive(Args, Debug)]
+struct AuthArgs {
+    /// An OAuth2 access token to use instead of the cached login.
+    #[arg(long, env = "MS_GRAPH_TOKEN", global = true, hide_env_values = true)]
+    token: Option<String>,
 
-        let new_item_count = self.collection.as_ref().unwrap().value.len();
+    /// The root url of the identity platform used to log in.
+    #[arg(long, env = "MS_GRAPH_AUTHORITY", global = true, default_value = "https://login.microsoftonline.com")]
+    authority: String,
 
-        // Copy all of the newly fetched items and put them into the `items` vec.
-        self.items.append(&mut self.collection.as_ref().unwrap().value.clone());
+    /// The client id of the application registration used to log in.
+    #[arg(long, env = "MS_GRAPH_CLIENT_ID", global = true, default_value = auth::DEFAULT_CLIENT_ID)]
+    client_id: String,
 
-        Ok(new_item_count)
-    }
+    /// The tenant to log in to: `common`, `consumers`, `organizations` or a tenant id.
+    #[arg(long, env = "MS_GRAPH_TENANT", global = true, default_value = "common")]
+    tenant: String,
 
-    /// Fetch the next page of items into the `items` property.
-    pub fn fetch_next(&mut self) -> Result<usize> {
-        if self.collection.is_none() {
-            return Ok(0);
-        }
-
-        let link = self.collection.as_ref().unwrap().odata.next_link.clone();
-        println!("{:?}", link);
+    /// The file in which the login tokens are cached.
+    #[arg(long, global = true, default_value = ".token-cache.json")]
+    token_cache: PathBuf,
+}
 
-        match link {
-            Some(link) => self.fetch_inner(&link),
-            None => Ok(0)
+impl AuthArgs {
+    fn config(&self) -> AuthConfig {
+        AuthConfig {
+            authority: self.authority.clone(),
+            client_id: self.client_id.clone(),
+            tenant: self.tenant.clone(),
+            cache_path: self.token_cache.clone(),
         }
     }
 
-    /// Does the collection have any further links (pages)?
-    pub fn has_next_link(&self) -> bool {
-        match &self.collection {
-            Some(c) => c.odata.next_link.is_some(),
-            None => false
-        }
+    /// Returns the token provider for Graph requests: the `--token` if one was given, otherwise the
+    /// cached login, logging in first when there is none.
+    fn tokens(&self) -> Result<Box<dyn TokenProvider>> {
+        auth::token_provider(self.token.as_deref(), self.config())
     }
 }
 
-impl<'a, T: DeserializeOwned + Clone> Iterator for CollectionReader<'a, T> {
-    type Item = T;
-
-    fn next(&mut self) -> Option<Self::Item> {
-        if self.collection.is_none() {
-            return None;
-        }
-
-        if self.collection.as_ref().unwrap().value.is_empty() {
-            return None;
-        }
-
-        // If we're at the last item in the currently loaded items
-        if self.iter_index == self.items.len() {
-            if !self.has_next_link() {
-                return None;
-            }
-
-            self.fetch_next().expect("Failed to fetch next items!");
-        }
+#[derive(Subcommand, Debug)]
+#[allow(clippy::large_enum_variant)]
+enum Command {
+    /// Log in with a device code and cache the tokens for later commands.
+    Login,
+
+    /// Work with Microsoft To Do lists and tasks.
+    Todo {
+        #[command(subcommand)]
+        command: TodoCommand,
+    },
+
+    /// Work with OneNote notebooks, sections and pages.
+    Onenote {
+        #[command(subcommand)]
+        command: OnenoteCommand,
+    },
+
+    /// Write OneNote pages or To Do tasks as Quake entries.
+    Quake {
+        #[command(subcommand)]
+        command: QuakeCommand,
+    },
+
+    /// Convert downloaded OneNote pages into another format.
+    Convert {
+        /// The directory containing the downloaded `.html` pages.
+        #[arg(long, default_value = "content")]
+        input: PathBuf,
+
+        /// The directory to write the converted pages into.
+        #[arg(long, default_value = "out")]
+        output: PathBuf,
+
+        /// The format to convert the pages into.
+        #[arg(long, value_enum, default_value_t = ConvertFormat::Markdown)]
+        format: ConvertFormat,
+    },
+}
 
-        if self.iter_index == self.items.len() {
-            return None;
-        }
+#[derive(Subcommand, Debug)]
+enum QuakeCommand {
+    /// Write the downloaded OneNote pages as entries.
+    Onenote {
+        /// The index written by `onenote index`, with or without `--flat`.
+        #[arg(long, short, default_value = "onenote-output.json")]
+        input: PathBuf,
+
+        /// The directory the pages were downloaded into by `onenote download-pages`.
+        #[arg(long, default_value = "content")]
+        content_dir: PathBuf,
+
+        /// The Quake workspace to write the entries into.
+        #[arg(long, default_value = "quake")]
+        output_dir: PathBuf,
+
+        /// The entry type, i.e. the directory of the workspace which the entries are written into.
+        #[arg(long, default_value = "onenote")]
+        entry_type: String,
+    },
+
+    /// Write the tasks of a JSON To Do export as entries.
+    Todo {
+        /// The JSON export written by `todo export` or `todo sync`.
+        #[arg(long, short, default_value = "output.json")]
+        input: PathBuf,
+
+        /// The Quake workspace to write the entries into.
+        #[arg(long, default_value = "quake")]
+        output_dir: PathBuf,
+
+        /// The entry type, i.e. the directory of the workspace which the entries are written into.
+        #[arg(long, default_value = "todo")]
+        entry_type: String,
+    },
+}
 
-        let fetch_index = self.iter_index;
-        self.iter_index += 1;
+#[derive(Subcommand, Debug)]
+enum TodoCommand {
+    /// Export every To Do list along with its tasks.
+    Export {
+        /// The file to write the export into.
+        #[arg(long, short, default_value = "output.json")]
+        output: PathBuf,
+
+        /// The format of the export.
+        #[arg(long, value_enum, default_value_t = ExportFormat::Json)]
+        format: ExportFormat,
+
+        /// The directory to download task attachments into, in a directory per list and task.
+        #[arg(long, default_value = "attachments")]
+        attachments_dir: PathBuf,
+
+        /// Don't download task attachments; they are still listed in the export.
+        #[arg(long)]
+        skip_attachments: bool,
+    },
+
+    /// Update a JSON export with only the lists and tasks changed since the last sync.
+    Sync {
+        /// The JSON export to update, which is created by the first sync.
+        #[arg(long, short, default_value = "output.json")]
+        output: PathBuf,
+
+        /// The file keeping the delta links of the last sync.
+        #[arg(long, default_value = "sync-state.json")]
+        state: PathBuf,
+
+        /// The directory to download task attachments into, in a directory per list and task.
+        #[arg(long, default_value = "attachments")]
+        attachments_dir: PathBuf,
+
+        /// Don't download task attachments; they are still listed in the export.
+        #[arg(long)]
+        skip_attachments: bool,
+    },
+
+    /// Recreate the lists and tasks of an export in the signed-in account.
+    Import {
+        /// The JSON export written by `todo export`.
+        #[arg(long, short, default_value = "output.json")]
+        input: PathBuf,
+
+        /// The file mapping exported ids to imported ones; an interrupted import resumes from it.
+        #[arg(long, default_value = "import-ids.json")]
+        id_map: PathBuf,
+    },
+
+    /// Copy every list and task of the signed-in account into a second, target account.
+    Migrate {
+        /// An OAuth2 access token for the target account, instead of its cached login.
+        #[arg(long, env = "MS_GRAPH_TARGET_TOKEN", hide_env_values = true)]
+        target_token: Option<String>,
+
+        /// The tenant of the target account: `common`, `consumers`, `organizations` or a tenant id.
+        #[arg(long, default_value = "common")]
+        target_tenant: String,
+
+        /// The file in which the login tokens of the target account are cached.
+        #[arg(long, default_value = ".target-token-cache.json")]
+        target_token_cache: PathBuf,
+
+        /// The root url of the Graph service of the target account; the source's when not given.
+        #[arg(long)]
+        target_graph_url: Option<String>,
+
+        /// The file mapping source ids to target ones; an interrupted migration resumes from it.
+        #[arg(long, default_value = "migrate-ids.json")]
+        id_map: PathBuf,
+    },
+}
 
-        self.items.get(fetch_index).map(|item_ref| item_ref.clone())
-    }
+#[derive(Subcommand, Debug)]
+#[allow(clippy::large_enum_variant)]
+enum OnenoteCommand {
+    /// Index every notebook, section group, section and page of the user's notebooks.
+    Index {
+        /// The id or user principal name of the user whose notebooks are indexed, or `me`.
+        #[arg(long, short, default_value = "me")]
+        user: String,
+
+        #[command(flatten)]
+        filter: FilterArgs,
+
+        /// The number of pages to request at a time; Graph's default when not given.
+        #[arg(long, value_name = "N")]
+        page_size: Option<u32>,
+
+        /// The order in which the pages of a section are listed, e.g. `lastModifiedDateTime desc`.
+        #[arg(long, value_name = "ORDER")]
+        order_by: Option<String>,
+
+        /// The page properties to request, separated by commas; the properties the index needs are always included.
+        #[arg(long, value_name = "PROPERTIES")]
+        select: Option<String>,
+
+        /// List the sections on their own, without the notebooks and section groups they are in.
+        #[arg(long)]
+        flat: bool,
+
+        /// The file to write the index into.
+        #[arg(long, short, default_value = "onenote-output.json")]
+        output: PathBuf,
+
+        /// The file to write the page content urls into.
+        #[arg(long, default_value = "urls")]
+        urls: PathBuf,
+    },
+
+    /// Download the content of every page listed in an index.
+    DownloadPages {
+        /// The index written by `onenote index`, with or without `--flat`.
+        #[arg(long, short, default_value = "onenote-output.json")]
+        input: PathBuf,
+
+        /// The directory to download the pages into.
+        #[arg(long, default_value = "content")]
+        output_dir: PathBuf,
+
+        /// The number of pages to download at the same time.
+        #[arg(long, short, default_value_t = 4)]
+        jobs: usize,
+
+        /// What to do with downloaded pages which are no longer in the index. By default they are archived,
+        /// unless the index was written with filters or `--flat`, in which case they are kept.
+        #[arg(long, value_enum)]
+        removed: Option<RemovedPages>,
+    },
+
+    /// Write the downloaded pages as Markdown, in a directory per notebook, section group and section.
+    Vault {
+        /// The index written by `onenote index`, with or without `--flat`.
+        #[arg(long, short, default_value = "onenote-output.json")]
+        input: PathBuf,
+
+        /// The directory the pages were downloaded into by `onenote download-pages`.
+        #[arg(long, default_value = "content")]
+        content_dir: PathBuf,
+
+        /// The directory to write the notebooks into.
+        #[arg(long, default_value = "vault")]
+        output_dir: PathBuf,
+    },
 }
 
+/// Include and exclude patterns for the notebooks, section groups and sections of a OneNote export.
+///
+/// Patterns are globs, e.g. `Work*`, or regular expressions when prefixed with `re:`.
+/// Each flag may be repeated.
+#[derive(Args, Debug)]
+struct FilterArgs {
+    /// Only export notebooks whose name matches this pattern.
+    #[arg(long = "notebook", value_name = "PATTERN")]
+    notebooks: Vec<String>,
+
+    /// Skip notebooks whose name matches this pattern.
+    #[arg(long = "exclude-notebook", value_name = "PATTERN")]
+    exclude_notebooks: Vec<String>,
+
+    /// Only export sections inside a section group whose name matches this pattern.
+    #[arg(long = "section-group", value_name = "PATTERN")]
+    section_groups: Vec<String>,
+
+    /// Skip sections inside a section group whose name matches this pattern.
+    #[arg(long = "exclude-section-group", value_name = "PATTERN")]
+    exclude_section_groups: Vec<String>,
+
+    /// Only export sections whose name matches this pattern.
+    #[arg(long = "section", value_name = "PATTERN")]
+    sections: Vec<String>,
+
+    /// Skip sections whose name matches this pattern.
+    #[arg(long = "exclude-section", value_name = "PATTERN")]
+    exclude_sections: Vec<String>,
+}
 
-fn graph_url(path: &str) -> String {
-    format!("{}{}", GRAPH_BASE_URI, path)
+impl FilterArgs {
+    fn to_filter(&self) -> Result<OnenoteFilter> {
+        Ok(OnenoteFilter {
+            notebooks: NameFilter::new(&self.notebooks, &self.exclude_notebooks)?,
+            section_groups: NameFilter::new(&self.section_groups, &self.exclude_section_groups)?,
+            sections: NameFilter::new(&self.sections, &self.exclude_sections)?,
+        })
+    }
 }
 
 #[derive(Serialize, Deserialize, Debug)]
@@ -149,143 +374,143 @@ pub struct OutputList {
     pub children: Vec<TodoTask>,
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+/// The index written by `onenote index`: every notebook, with its sections and section groups.
+#[derive(Serialize, Deserialize, Debug, Default)]
 #[serde(rename_all = "camelCase")]
 pub struct OnenoteVO {
     pub notebooks: Vec<NotebookVO>,
-}
 
-impl Default for OnenoteVO {
-    fn default() -> Self {
-        OnenoteVO {
-            notebooks: vec![]
-        }
-    }
+    /// Whether the index was written with filters, and so may leave out pages which still exist.
+    #[serde(default)]
+    pub filtered: bool,
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Default)]
 #[serde(rename_all = "camelCase")]
 pub struct NotebookVO {
-    pub sourceUrl: String,
+    pub source_url: String,
     pub id: String,
-    pub createdDateTime: String,
-    pub displayName: String,
-    pub lastModifiedDateTime: String,
+    pub created_date_time: String,
+    pub display_name: String,
+    pub last_modified_date_time: String,
     pub sections: Vec<SectionVO>,
+    #[serde(default)]
+    pub section_groups: Vec<SectionGroupVO>,
 }
 
-impl Default for NotebookVO {
-    fn default() -> Self {
-        NotebookVO {
-            sourceUrl: "".to_string(),
-            id: "".to_string(),
-            createdDateTime: "".to_string(),
-            displayName: "".to_string(),
-            lastModifiedDateTime: "".to_string(),
-            sections: vec![],
-        }
-    }
+/// A section group, with its sections and the section groups nested in it.
+#[derive(Serialize, Deserialize, Debug, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct SectionGroupVO {
+    pub source_url: String,
+    pub id: String,
+    pub created_date_time: String,
+    pub display_name: String,
+    pub last_modified_date_time: String,
+    pub sections: Vec<SectionVO>,
+    pub section_groups: Vec<SectionGroupVO>,
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Default)]
 #[serde(rename_all = "camelCase")]
 pub struct SectionVO {
-    pub sourceUrl: String,
+    pub source_url: String,
     pub id: String,
-    pub createdDateTime: String,
-    pub displayName: String,
-    pub lastModifiedDateTime: String,
-    pub parentName: String,
+    pub created_date_time: String,
+    pub display_name: String,
+    pub last_modified_date_time: String,
+    pub parent_name: String,
+    /// The names of the notebook and section groups the section is in, outermost first.
+    #[serde(default)]
+    pub path: Vec<String>,
     pub pages: Vec<PageVO>,
 }
 
-impl Default for SectionVO {
-    fn default() -> Self {
-        SectionVO {
-            sourceUrl: "".to_string(),
-            id: "".to_string(),
-            createdDateTime: "".to_string(),
-            displayName: "".to_string(),
-            lastModifiedDateTime: "".to_string(),
-            parentName: "".to_string(),
-            pages: vec![],
-        }
-    }
-}
-
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Default)]
 #[serde(rename_all = "camelCase")]
 pub struct PageVO {
-    pub sourceUrl: String,
+    pub source_url: String,
     pub id: String,
-    pub createdDateTime: String,
-    pub lastModifiedDateTime: String,
+    pub created_date_time: String,
+    pub last_modified_date_time: String,
     pub title: String,
-    pub contentUrl: String,
-}
-
-impl Default for PageVO {
-    fn default() -> Self {
-        PageVO {
-            sourceUrl: "".to_string(),
-            id: "".to_string(),
-            createdDateTime: "".to_string(),
-            lastModifiedDateTime: "".to_string(),
-            title: "".to_string(),
-            contentUrl: "".to_string(),
-        }
-    }
+    pub content_url: String,
 }
 
-
 fn main() -> Result<()> {
-    // To acquire OAuth token, grant all "Tasks" permissions within MS Graph Explorer, then click "Access Token"
-    // See: https://blog.osull.com/2020/09/14/backup-migrate-microsoft-to-do-tasks-with-powershell-and-microsoft-graph/
-    // See: https://gotoguy.blog/2020/05/06/oauth-authentication-to-microsoft-graph-with-powershell-core/
-    println!("Paste OAuth2 Token");
-
-    let mut token = String::new();
-    io::stdin().read_line(&mut token).expect("Failed to read line");
-    let token = token.trim();
-
-    onenote::dump_onenotes(token);
-
-    // let user_id = "";
-    // let client = Graph::new(token);
-    // download_pages(&client, user_id);
-
-    Ok(())
-}
-
-fn download_pages(client: &Graph<BlockingHttpClient>, user_id: &str) {
-    let content = fs::read_to_string("sections-output.json").unwrap();
-    let sections: Vec<SectionVO> = serde_json::from_str(&content).unwrap();
-
-    let mut urls: Vec<String> = vec![];
-    let mut id_url_map: HashMap<String, String> = HashMap::new();
-    for section in &sections {
-        for page in &section.pages {
-            id_url_map.insert(page.id.clone(), page.contentUrl.clone());
-            urls.push(page.contentUrl.clone());
-        }
-    }
-
-    fs::write("urls", urls.join("\n")).unwrap();
-
-    let mut fails = download(&client, &mut id_url_map, user_id);
-
-    while !fails.is_empty() {
-        fails = download(&client, &mut id_url_map, user_id);
-    }
-}
-
-fn download(client: &Graph<BlockingHttpClient>, id_url_map: &mut HashMap<String, String>, user_id: &str) -> HashMap<String, String> {
-    let mut fails: HashMap<String, String> = HashMap::new();
-    for (id, url) in id_url_map {
-        println!("downloading {:?}", url);
-        if download_page(&client, user_id, id.as_str()).is_err() {
-            fails.insert(id.to_string(), url.to_string());
+    let cli = Cli::parse();
+    let retry = RetryPolicy {
+        max_attempts: cli.max_attempts.max(1),
+        max_delay: Duration::from_secs(cli.max_delay),
+        ..RetryPolicy::default()
+    };
+    let endpoint = GraphEndpoint { base_url: cli.graph_url.clone(), version: cli.api_version.clone() };
+
+    match cli.command {
+        Command::Login => {
+            auth::login(&cli.auth.config())?;
+            println!("Logged in, tokens cached in {}", cli.auth.token_cache.display());
+            Ok(())
         }
+        Command::Todo { command } => match command {
+            TodoCommand::Export { output, format, attachments_dir, skip_attachments } => {
+                let attachments_dir = Some(attachments_dir.as_path()).filter(|_| !skip_attachments);
+                let tokens = cli.auth.tokens()?;
+                let client = GraphClient::new(tokens.as_ref(), endpoint, retry);
+                todo::dump_todos(&client, &output, format, attachments_dir)
+            }
+            TodoCommand::Sync { output, state, attachments_dir, skip_attachments } => {
+                let attachments_dir = Some(attachments_dir.as_path()).filter(|_| !skip_attachments);
+                let tokens = cli.auth.tokens()?;
+                let client = GraphClient::new(tokens.as_ref(), endpoint, retry);
+                sync::sync_todos(&client, &output, &state, attachments_dir)
+            }
+            TodoCommand::Import { input, id_map } => {
+                let tokens = cli.auth.tokens()?;
+                let client = GraphClient::new(tokens.as_ref(), endpoint, retry);
+                import::import_todos(&client, &input, &id_map)
+            }
+            TodoCommand::Migrate { target_token, target_tenant, target_token_cache, target_graph_url, id_map } => {
+                let target_config = AuthConfig {
+                    tenant: target_tenant,
+                    cache_path: target_token_cache,
+                    ..cli.auth.config()
+                };
+                let target_endpoint = GraphEndpoint {
+                    base_url: target_graph_url.unwrap_or_else(|| endpoint.base_url.clone()),
+                    ..endpoint.clone()
+                };
+
+                let tokens = cli.auth.tokens()?;
+                println!("Signed in to the source account; now signing in to the target account.");
+                let target_tokens = auth::token_provider(target_token.as_deref(), target_config)?;
+
+                let source = GraphClient::new(tokens.as_ref(), endpoint, retry);
+                let target = GraphClient::new(target_tokens.as_ref(), target_endpoint, retry);
+                import::migrate_todos(&source, &target, &id_map)
+            }
+        },
+        Command::Onenote { command } => match command {
+            OnenoteCommand::Index { user, filter, page_size, order_by, select, flat, output, urls } => {
+                let filter = filter.to_filter()?;
+                let page_query = PageQuery { top: page_size, order_by, select };
+                let tokens = cli.auth.tokens()?;
+                let client = GraphClient::new(tokens.as_ref(), endpoint, retry);
+                onenote::dump_onenotes(&client, &user, &filter, page_query, flat, &output, &urls)
+            }
+            OnenoteCommand::DownloadPages { input, output_dir, jobs, removed } => {
+                let tokens = cli.auth.tokens()?;
+                let client = GraphClient::new(tokens.as_ref(), endpoint, retry);
+                onenote::download_pages(&client, &input, &output_dir, jobs, removed)
+            }
+            OnenoteCommand::Vault { input, content_dir, output_dir } => vault::export_vault(&input, &content_dir, &output_dir),
+        },
+        Command::Quake { command } => match command {
+            QuakeCommand::Onenote { input, content_dir, output_dir, entry_type } => {
+                quake::write_onenote_entries(&input, &content_dir, &output_dir, &entry_type)
+            }
+            QuakeCommand::Todo { input, output_dir, entry_type } => quake::write_todo_entries(&input, &output_dir, &entry_type),
+        },
+        Command::Convert { input, output, format } => convert::convert(&input, &output, format),
     }
-    fails
 }
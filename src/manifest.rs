@@ -0,0 +1,86 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::{self, Read, Write};
+use std::path::Path;
+
+use sha2::{Digest, Sha256};
+
+use crate::error::Result;
+use crate::files::JsonFile;
+
+/// The pages downloaded by earlier runs of `download_pages`, by page id, so that only the pages
+/// which changed since are downloaded again.
+#[derive(Serialize, Deserialize, Debug, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct Manifest {
+    pub pages: BTreeMap<String, ManifestEntry>,
+}
+
+/// A page as it was when it was downloaded.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ManifestEntry {
+    /// The `lastModifiedDateTime` of the page when it was downloaded.
+    pub last_modified_date_time: String,
+
+    /// The SHA-256 of the downloaded content, as lowercase hex.
+    pub sha256: String,
+
+    /// The downloaded file, relative to the download directory.
+    pub file: String,
+}
+
+impl JsonFile for Manifest {}
+
+/// A writer which computes the SHA-256 of everything written through it.
+pub struct HashingWriter<W> {
+    inner: W,
+    hasher: Sha256,
+}
+
+impl<W: Write> HashingWriter<W> {
+    pub fn new(inner: W) -> Self {
+        Self { inner, hasher: Sha256::new() }
+    }
+
+    /// The SHA-256 of everything written so far, as lowercase hex.
+    pub fn finish(self) -> String {
+        to_hex(&self.hasher.finalize())
+    }
+}
+
+impl<W: Write> Write for HashingWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let written = self.inner.write(buf)?;
+        self.hasher.update(&buf[..written]);
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// The SHA-256 of the file at `path`, as lowercase hex.
+pub fn sha256_file(path: &Path) -> Result<String> {
+    let mut file = fs::File::open(path)?;
+    let mut hasher = Sha256::new();
+    let mut buf = [0; 8192];
+    loop {
+        let read = file.read(&mut buf)?;
+        if read == 0 {
+            break;
+        }
+        hasher.update(&buf[..read]);
+    }
+    Ok(to_hex(&hasher.finalize()))
+}
+
+/// The SHA-256 of `bytes`, as lowercase hex.
+pub fn sha256(bytes: &[u8]) -> String {
+    to_hex(&Sha256::digest(bytes))
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
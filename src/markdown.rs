@@ -0,0 +1,378 @@
+use ego_tree::NodeRef;
+use scraper::{ElementRef, Html, Node, Selector};
+
+/// Convert a OneNote page, as returned by the page content endpoint, into Markdown.
+///
+/// The page title becomes the top level heading. OneNote places the content of a page in absolutely
+/// positioned `div`s, which are converted one after another from top to bottom, and left to right when
+/// side by side. Paragraphs and list items tagged as to-dos (`data-tag="to-do"` or `data-tag="to-do:completed"`)
+/// become task list items. Text which Markdown would read as formatting is escaped.
+pub fn convert_page(html: &str) -> String {
+    let document = Html::parse_document(html);
+    let mut blocks = vec![];
+
+    let title = Selector::parse("title").unwrap();
+    if let Some(title) = document.select(&title).next() {
+        let title = collapse_whitespace(&escape(&title.text().collect::<String>()));
+        if !title.trim().is_empty() {
+            blocks.push(format!("# {}", title.trim()));
+        }
+    }
+
+    let body = Selector::parse("body").unwrap();
+    if let Some(body) = document.select(&body).next() {
+        render_nodes(in_layout_order(*body), &mut blocks);
+    }
+
+    let mut markdown = blocks.join("\n\n");
+    markdown.push('\n');
+    markdown
+}
+
+/// Elements which are rendered as blocks of their own, rather than inline with the text around them.
+fn is_block(name: &str) -> bool {
+    matches!(
+        name,
+        "address" | "article" | "aside" | "blockquote" | "body" | "div" | "dl" | "footer" | "form"
+            | "h1" | "h2" | "h3" | "h4" | "h5" | "h6" | "header" | "hr" | "main" | "nav" | "ol"
+            | "p" | "pre" | "section" | "table" | "ul" | "script" | "style" | "noscript"
+    )
+}
+
+/// The children of `body`, with its absolutely positioned `div`s sorted by their `top`, then their `left`.
+/// Everything else keeps its place.
+fn in_layout_order(body: NodeRef<Node>) -> Vec<NodeRef<Node>> {
+    let mut children: Vec<NodeRef<Node>> = body.children().collect();
+    let position = |node: &NodeRef<Node>| -> Option<(f64, f64)> {
+        let element = node.value().as_element().filter(|element| element.name() == "div")?;
+        let style = element.attr("style")?;
+        Some((style_px(style, "top")?, style_px(style, "left").unwrap_or(0.0)))
+    };
+
+    let indices: Vec<usize> = (0..children.len()).filter(|&i| position(&children[i]).is_some()).collect();
+    let mut positioned: Vec<NodeRef<Node>> = indices.iter().map(|&i| children[i]).collect();
+    positioned.sort_by(|a, b| position(a).partial_cmp(&position(b)).unwrap_or(std::cmp::Ordering::Equal));
+    for (i, node) in indices.into_iter().zip(positioned) {
+        children[i] = node;
+    }
+    children
+}
+
+/// The pixel value of `property` in the inline `style`, e.g. `115` for `top:115px`.
+fn style_px(style: &str, property: &str) -> Option<f64> {
+    style.split(';')
+        .filter_map(|declaration| declaration.split_once(':'))
+        .find(|(name, _)| name.trim() == property)
+        .and_then(|(_, value)| value.trim().trim_end_matches("px").parse().ok())
+}
+
+/// Render the children of `node` as Markdown blocks, collecting consecutive inline content into paragraphs.
+fn render_blocks(node: NodeRef<Node>, blocks: &mut Vec<String>) {
+    render_nodes(node.children().collect(), blocks);
+}
+
+/// Render `nodes` as Markdown blocks, like `render_blocks`.
+fn render_nodes(nodes: Vec<NodeRef<Node>>, blocks: &mut Vec<String>) {
+    let mut paragraph = String::new();
+    for child in nodes {
+        match child.value() {
+            Node::Element(element) if is_block(element.name()) => {
+                push_paragraph(&mut paragraph, blocks);
+                render_block(child, blocks);
+            }
+            _ => paragraph.push_str(&render_inline(child)),
+        }
+    }
+    push_paragraph(&mut paragraph, blocks);
+}
+
+/// Push the inline content collected in `paragraph`, if any, as a block.
+fn push_paragraph(paragraph: &mut String, blocks: &mut Vec<String>) {
+    let text = hard_breaks(&escape_line_starts(&trim_lines(paragraph)));
+    if !text.is_empty() {
+        blocks.push(text);
+    }
+    paragraph.clear();
+}
+
+/// Render the block level element `node` into `blocks`.
+fn render_block(node: NodeRef<Node>, blocks: &mut Vec<String>) {
+    let element = match ElementRef::wrap(node) {
+        Some(element) => element,
+        None => return,
+    };
+
+    let name = element.value().name();
+    match name {
+        "h1" | "h2" | "h3" | "h4" | "h5" | "h6" => {
+            let level = name[1..].parse().unwrap_or(1);
+            let text = trim_lines(&render_children_inline(node)).replace('\n', " ");
+            if !text.is_empty() {
+                blocks.push(format!("{} {}", "#".repeat(level), text));
+            }
+        }
+        "p" => {
+            let text = hard_breaks(&escape_line_starts(&trim_lines(&render_children_inline(node))));
+            if text.is_empty() {
+                return;
+            }
+            match todo_checkbox(element) {
+                Some(checkbox) => blocks.push(format!("- {} {}", checkbox, text)),
+                None => blocks.push(text),
+            }
+        }
+        "ul" | "ol" => {
+            let list = render_list(element);
+            if !list.is_empty() {
+                blocks.push(list.join("\n"));
+            }
+        }
+        "table" => {
+            let table = render_table(element);
+            if !table.is_empty() {
+                blocks.push(table);
+            }
+        }
+        "pre" => blocks.push(format!("```\n{}\n```", element.text().collect::<String>().trim_end())),
+        "hr" => blocks.push("---".to_string()),
+        "blockquote" => {
+            let mut inner = vec![];
+            render_blocks(node, &mut inner);
+            let quoted: Vec<String> = inner.join("\n\n").lines()
+                .map(|line| if line.is_empty() { ">".to_string() } else { format!("> {}", line) })
+                .collect();
+            if !quoted.is_empty() {
+                blocks.push(quoted.join("\n"));
+            }
+        }
+        "script" | "style" | "noscript" => {}
+        _ => render_blocks(node, blocks),
+    }
+}
+
+/// The task list checkbox of a paragraph or list item tagged as a OneNote to-do, if it is one.
+///
+/// `data-tag` may hold several tags separated by commas, e.g. `important,to-do:completed`.
+fn todo_checkbox(element: ElementRef) -> Option<&'static str> {
+    let tags = element.value().attr("data-tag")?;
+    tags.split(',').map(|tag| tag.trim()).find_map(|tag| match tag {
+        "to-do" => Some("[ ]"),
+        "to-do:completed" => Some("[x]"),
+        _ => None,
+    })
+}
+
+/// Render a `ul` or `ol` element into lines, with nested lists indented below their items.
+fn render_list(list: ElementRef) -> Vec<String> {
+    let ordered = list.value().name() == "ol";
+    let start: usize = list.value().attr("start").and_then(|start| start.parse().ok()).unwrap_or(1);
+    let items = list.children().filter_map(ElementRef::wrap).filter(|e| e.value().name() == "li");
+
+    let mut lines = vec![];
+    for (number, item) in (start..).zip(items) {
+        let marker = if ordered { format!("{}.", number) } else { "-".to_string() };
+
+        let mut text = String::new();
+        let mut nested = vec![];
+        let mut checkbox = todo_checkbox(item);
+        for child in item.children() {
+            match ElementRef::wrap(child) {
+                Some(e) if matches!(e.value().name(), "ul" | "ol") => nested.extend(render_list(e)),
+                Some(e) if is_block(e.value().name()) => {
+                    checkbox = checkbox.or_else(|| todo_checkbox(e));
+                    text.push(' ');
+                    text.push_str(&render_children_inline(child));
+                }
+                _ => text.push_str(&render_inline(child)),
+            }
+        }
+
+        let text = escape_line_starts(&trim_lines(&text).replace('\n', " "));
+        match checkbox {
+            Some(checkbox) => lines.push(format!("{} {} {}", marker, checkbox, text).trim_end().to_string()),
+            None => lines.push(format!("{} {}", marker, text).trim_end().to_string()),
+        }
+
+        let indent = " ".repeat(marker.len() + 1);
+        lines.extend(nested.into_iter().map(|line| format!("{}{}", indent, line)));
+    }
+    lines
+}
+
+/// Render a `table` element as a pipe table, using the first row as the header.
+///
+/// OneNote tables have no header cells; cells holding several paragraphs have them joined with `<br>`.
+fn render_table(table: ElementRef) -> String {
+    let mut rows: Vec<Vec<String>> = vec![];
+    let sections = table.children().filter_map(ElementRef::wrap)
+        .flat_map(|child| match child.value().name() {
+            "thead" | "tbody" | "tfoot" => child.children().filter_map(ElementRef::wrap).collect::<Vec<_>>(),
+            _ => vec![child],
+        });
+
+    for row in sections.filter(|e| e.value().name() == "tr") {
+        let cells = row.children().filter_map(ElementRef::wrap)
+            .filter(|cell| matches!(cell.value().name(), "td" | "th"))
+            .map(|cell| {
+                let mut blocks = vec![];
+                render_blocks(*cell, &mut blocks);
+                blocks.join("<br>").replace('\n', "<br>").replace('|', "\\|")
+            })
+            .collect();
+        rows.push(cells);
+    }
+
+    let columns = rows.iter().map(|row| row.len()).max().unwrap_or(0);
+    if columns == 0 {
+        return String::new();
+    }
+
+    let mut lines = vec![];
+    for (i, row) in rows.iter().enumerate() {
+        let mut cells = row.clone();
+        cells.resize(columns, String::new());
+        lines.push(format!("| {} |", cells.join(" | ")));
+        if i == 0 {
+            lines.push(format!("|{}", " --- |".repeat(columns)));
+        }
+    }
+    lines.join("\n")
+}
+
+/// Render the children of `node` as inline Markdown.
+fn render_children_inline(node: NodeRef<Node>) -> String {
+    node.children().map(render_inline).collect()
+}
+
+/// Render `node` as inline Markdown.
+fn render_inline(node: NodeRef<Node>) -> String {
+    let element = match node.value() {
+        Node::Text(text) => return collapse_whitespace(&escape(text)),
+        Node::Element(element) => element,
+        _ => return String::new(),
+    };
+
+    let inner = || render_children_inline(node);
+    match element.name() {
+        "br" => "\n".to_string(),
+        "b" | "strong" => emphasize(&inner(), "**"),
+        "i" | "em" => emphasize(&inner(), "*"),
+        "s" | "del" | "strike" => emphasize(&inner(), "~~"),
+        // Code is written as it is, as Markdown doesn't read escapes within it.
+        "code" => {
+            let code: String = node.descendants().filter_map(|n| n.value().as_text().map(|text| text.to_string())).collect();
+            format!("`{}`", collapse_whitespace(&code).trim())
+        }
+        "a" => match element.attr("href") {
+            Some(href) if !inner().trim().is_empty() => format!("[{}]({})", inner().trim(), destination(href)),
+            Some(href) => format!("<{}>", href),
+            None => inner(),
+        },
+        "img" => match element.attr("src") {
+            Some(src) => format!("![{}]({})", escape(element.attr("alt").unwrap_or_default()), destination(src)),
+            None => String::new(),
+        },
+        "object" => match element.attr("data") {
+            Some(data) => format!("[{}]({})", element.attr("data-attachment").unwrap_or("attachment"), destination(data)),
+            None => String::new(),
+        },
+        // OneNote marks up formatting with inline styles rather than elements.
+        "span" => {
+            let style = element.attr("style").unwrap_or_default().replace(' ', "");
+            let mut text = inner();
+            if style.contains("font-weight:bold") {
+                text = emphasize(&text, "**");
+            }
+            if style.contains("font-style:italic") {
+                text = emphasize(&text, "*");
+            }
+            if style.contains("text-decoration:line-through") {
+                text = emphasize(&text, "~~");
+            }
+            text
+        }
+        "script" | "style" | "head" | "title" => String::new(),
+        _ => inner(),
+    }
+}
+
+/// The link destination for `url`, which is enclosed in `<>` when it contains spaces, as the local
+/// copies of attached files may.
+fn destination(url: &str) -> String {
+    if url.contains(' ') {
+        format!("<{}>", url)
+    } else {
+        url.to_string()
+    }
+}
+
+/// Wrap `text` in `marker`, keeping any surrounding whitespace outside of the markers.
+fn emphasize(text: &str, marker: &str) -> String {
+    let trimmed = text.trim();
+    if trimmed.is_empty() {
+        return text.to_string();
+    }
+
+    let leading = &text[..text.len() - text.trim_start().len()];
+    let trailing = &text[text.trim_end().len()..];
+    format!("{}{}{}{}{}", leading, marker, trimmed, marker, trailing)
+}
+
+/// Escape the characters of `text` which Markdown would read as emphasis, code, links or HTML.
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        if matches!(c, '\\' | '*' | '_' | '`' | '[' | ']' | '~' | '<') {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+/// Escape the start of every line of `text` which Markdown would read as a heading, list item or quote,
+/// e.g. `# 1` or `2021. A good year`.
+fn escape_line_starts(text: &str) -> String {
+    let lines: Vec<String> = text.lines()
+        .map(|line| {
+            if line.starts_with(['#', '-', '+', '>']) {
+                return format!("\\{}", line);
+            }
+            let digits = line.len() - line.trim_start_matches(|c: char| c.is_ascii_digit()).len();
+            if digits > 0 && line[digits..].starts_with(['.', ')']) {
+                return format!("{}\\{}", &line[..digits], &line[digits..]);
+            }
+            line.to_string()
+        })
+        .collect();
+    lines.join("\n")
+}
+
+/// Replace every run of whitespace in `text` with a single space, as browsers do.
+fn collapse_whitespace(text: &str) -> String {
+    let mut collapsed = String::with_capacity(text.len());
+    let mut in_whitespace = false;
+    for c in text.chars() {
+        if c.is_whitespace() {
+            if !in_whitespace {
+                collapsed.push(' ');
+            }
+            in_whitespace = true;
+        } else {
+            collapsed.push(c);
+            in_whitespace = false;
+        }
+    }
+    collapsed
+}
+
+/// Turn the line breaks in `text`, which come from `br` elements, into Markdown hard line breaks.
+fn hard_breaks(text: &str) -> String {
+    text.replace('\n', "\\\n")
+}
+
+/// Trim every line of `text`, and drop the leading and trailing empty lines.
+fn trim_lines(text: &str) -> String {
+    let lines: Vec<&str> = text.lines().map(|line| line.trim()).collect();
+    lines.join("\n").trim().to_string()
+}
@@ -1,267 +1,624 @@
-use std::ffi::OsString;
+use std::collections::{HashMap, HashSet};
 use std::fs;
-use std::path::PathBuf;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread;
+
+use scraper::{Html, Selector};
+use quake_microsoft_todo::onenote::{Notebook, OnenotePage, OnenoteResource, OnenoteSection, SectionGroup};
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+
+use crate::error::{Error, Result};
+use crate::files::{self, JsonFile};
+use crate::filter::OnenoteFilter;
+use crate::http::GraphClient;
+use crate::manifest::{self, HashingWriter, Manifest, ManifestEntry};
+use crate::progress::Progress;
+use crate::{NotebookVO, OnenoteVO, PageVO, SectionGroupVO, SectionVO};
+
+/// The page properties the section index is built from, which are always requested.
+const PAGE_PROPERTIES: [&str; 5] = ["id", "title", "createdDateTime", "lastModifiedDateTime", "contentUrl"];
+
+/// The OData query options used when listing the pages of a section.
+#[derive(Debug, Clone, Default)]
+pub struct PageQuery {
+    /// The number of pages requested at a time (`$top`); Graph's own default when `None`.
+    pub top: Option<u32>,
+
+    /// The order of the pages (`$orderby`), e.g. `lastModifiedDateTime desc`.
+    pub order_by: Option<String>,
+
+    /// The page properties to request (`$select`), separated by commas. `PAGE_PROPERTIES` are always added.
+    pub select: Option<String>,
+}
 
-use graph_http::{BlockingDownloadError, BlockingHttpClient, GraphResponse};
-use graph_http::serde_json::Value;
-use graph_http::traits::ODataLink;
-use graph_rs_sdk::client::Graph;
+impl PageQuery {
+    /// The query string for these options, including the leading `?`, or an empty string when there are none.
+    fn to_query(&self) -> String {
+        let mut options = vec![];
+        if let Some(top) = self.top {
+            options.push(format!("$top={}", top));
+        }
+        if let Some(order_by) = &self.order_by {
+            options.push(format!("$orderby={}", order_by.replace(' ', "%20")));
+        }
+        if let Some(select) = &self.select {
+            let mut properties: Vec<&str> = select.split(',').map(|p| p.trim()).filter(|p| !p.is_empty()).collect();
+            for property in PAGE_PROPERTIES.iter() {
+                if !properties.contains(property) {
+                    properties.push(property);
+                }
+            }
+            options.push(format!("$select={}", properties.join(",")));
+        }
 
-use crate::{NotebookVO, OnenoteVO, PageVO, SectionVO};
+        if options.is_empty() {
+            String::new()
+        } else {
+            format!("?{}", options.join("&"))
+        }
+    }
+}
+
+/// A Graph client for the OneNote resource of a single user.
+pub struct OnenoteClient<'a> {
+    client: &'a GraphClient<'a>,
+
+    /// Either `me` (the signed-in user) or the id or user principal name of a user.
+    user_id: &'a str,
+
+    /// The query options used when listing pages.
+    page_query: PageQuery,
+}
+
+impl<'a> OnenoteClient<'a> {
+    pub fn new(client: &'a GraphClient<'a>, user_id: &'a str, page_query: PageQuery) -> Self {
+        Self { client, user_id, page_query }
+    }
 
-pub fn dump_onenotes(token: &str) {
-    let client = Graph::new(token);
-    let user_id: &str = "gmszone@live.com";
+    /// The url of `path` within the user's OneNote resource.
+    fn url(&self, path: &str) -> String {
+        if self.user_id.trim_start_matches('/') == "me" {
+            self.client.url(&format!("/me/onenote{}", path))
+        } else {
+            self.client.url(&format!("/users/{}/onenote{}", self.user_id, path))
+        }
+    }
 
-    // let (urls, onenote_vo) = download_sections_from_top(&client, user_id);
+    /// List every item of the collection at `path` within the user's OneNote resource, following next links,
+    /// and return them along with the url of the collection.
+    ///
+    /// A request which fails fails the whole listing, so that an index is never written with parts missing:
+    /// the pages missing from it would be taken for pages removed upstream.
+    fn list<T: DeserializeOwned>(&self, path: &str) -> Result<(String, Vec<T>)> {
+        let url = self.url(path);
+        let items = self.client.pages::<T>(&url).collect::<Result<Vec<T>>>()?;
+        Ok((url, items))
+    }
+}
 
-    let get_sections = client
-        .v1()
-        .user(user_id)
-        .onenote()
-        .list_sections()
-        .send();
+/// Index the notebooks, section groups, sections and pages of `user_id`'s notebooks which pass `filter`, writing
+/// the notebook tree (`OnenoteVO`) into `output` and the page content urls into `urls_output`. Pages are listed
+/// with the `page_query` options.
+///
+/// With `flat`, the sections are listed on their own instead, without the notebooks and section groups they are in.
+pub fn dump_onenotes(client: &GraphClient, user_id: &str, filter: &OnenoteFilter, page_query: PageQuery, flat: bool, output: &Path, urls_output: &Path) -> Result<()> {
+    let client = OnenoteClient::new(client, user_id, page_query);
 
     let mut urls = vec![];
-    let mut all_sections = vec![];
     let mut page_index = 1;
-    match get_sections {
-        Ok(section) => {
-            let mut sections = build_sections(&client, user_id, &mut urls, section, &mut page_index);
-            all_sections.append(&mut sections);
+    let string = if flat {
+        serde_json::to_string(&fetch_all_sections(&client, filter, &mut urls, &mut page_index)?)?
+    } else {
+        let mut onenote_vo = fetch_notebooks(&client, filter, &mut urls, &mut page_index)?;
+        onenote_vo.filtered = !filter.is_empty();
+        serde_json::to_string(&onenote_vo)?
+    };
+    fs::write(output, string)?;
+
+    fs::write(urls_output, urls.join("\n"))?;
+
+    Ok(())
+}
+
+/// Fetch every section of the user which passes `filter`, from the flat `/sections` listing.
+///
+/// Sections only name their notebook and the section group right above them, so every section group is listed
+/// as well to find the groups further up, which make up the path of a section and are filtered like in the tree.
+fn fetch_all_sections(client: &OnenoteClient, filter: &OnenoteFilter, urls: &mut Vec<String>, page_index: &mut usize) -> Result<Vec<SectionVO>> {
+    let (sections_url, sections) = client.list::<OnenoteSection>("/sections")?;
+    let (_, section_groups) = client.list::<SectionGroup>("/sectionGroups")?;
+    let section_groups: HashMap<String, SectionGroup> = section_groups.into_iter()
+        .map(|section_group| (section_group.id.clone(), section_group))
+        .collect();
+
+    let mut section_vos = vec![];
+    for section in sections {
+        let notebook_name = section.parent_notebook.as_ref().and_then(|notebook| notebook.display_name.clone());
+        let group_names = section_group_names(&section, &section_groups);
+        if !filter.notebooks.matches_optional(notebook_name.as_deref()) || !filter.section_groups.matches_nested(&group_names) {
+            continue;
         }
-        Err(err) => {
-            println!("{:?}", err);
+
+        let path: Vec<String> = notebook_name.into_iter().chain(group_names).collect();
+        section_vos.extend(build_sections(client, filter, urls, &sections_url, vec![section], page_index, &path)?);
+    }
+
+    Ok(section_vos)
+}
+
+/// The names of the section groups `section` is in, outermost first, following the `parentSectionGroup`
+/// of each group in `section_groups`.
+fn section_group_names(section: &OnenoteSection, section_groups: &HashMap<String, SectionGroup>) -> Vec<String> {
+    let mut names = vec![];
+    let mut parent = section.parent_section_group.clone();
+    while let Some(section_group) = parent {
+        // Groups nest a few levels deep at most; a parent pointing back down would be a Graph bug.
+        if names.len() > section_groups.len() {
+            break;
         }
+
+        let section_group = section_groups.get(&section_group.id).cloned().unwrap_or(section_group);
+        names.push(section_group.display_name.unwrap_or_default());
+        parent = section_group.parent_section_group.map(|section_group| *section_group);
     }
 
-    let string = serde_json::to_string(&all_sections).unwrap();
-    fs::write("sections-output.json", string).unwrap();
+    names.reverse();
+    names
+}
+
+/// Fetch every notebook of the user which passes `filter`, with its sections and section groups.
+fn fetch_notebooks(client: &OnenoteClient, filter: &OnenoteFilter, urls: &mut Vec<String>, page_index: &mut usize) -> Result<OnenoteVO> {
+    let (notebooks_url, notebooks) = client.list::<Notebook>("/notebooks")?;
 
-    fs::write("urls", urls.join("\n")).unwrap();
+    let mut onenote_vo = OnenoteVO::default();
+    for notebook in notebooks {
+        let book_name = notebook.display_name.unwrap_or_default();
+        if !filter.notebooks.matches(&book_name) {
+            continue;
+        }
+
+        println!("bookName: {:}", book_name);
+
+        let path = vec![book_name.clone()];
+        let mut notebook_vo = NotebookVO {
+            source_url: notebooks_url.clone(),
+            id: notebook.id,
+            created_date_time: notebook.created_date_time.unwrap_or_default(),
+            display_name: book_name,
+            last_modified_date_time: notebook.last_modified_date_time.unwrap_or_default(),
+            sections: vec![],
+            section_groups: vec![],
+        };
+
+        // Like in the flat index, sections outside of any group only pass when no group must match.
+        if filter.section_groups.matches_optional(None) {
+            let (sections_url, sections) = client.list(&format!("/notebooks/{}/sections", notebook_vo.id))?;
+            notebook_vo.sections = build_sections(client, filter, urls, &sections_url, sections, page_index, &path)?;
+        }
+
+        let groups_path = format!("/notebooks/{}/sectionGroups", notebook_vo.id);
+        notebook_vo.section_groups = fetch_section_groups(client, filter, urls, page_index, &groups_path, &path, false)?;
+
+        onenote_vo.notebooks.push(notebook_vo);
+    }
+
+    Ok(onenote_vo)
 }
 
-fn download_sections_from_top(client: &Graph<BlockingHttpClient>, user_id: &str) {
-    let notebooks = client
-        .v1()
-        .user(user_id)
-        .onenote()
-        .notebooks()
-        .list_notebooks()
-        .send();
+/// Fetch the section groups listed at `groups_path`, along with their sections and, recursively,
+/// the section groups nested in them. `path` holds the names of the notebook and groups they are in.
+///
+/// A group matching an exclude pattern is skipped along with everything in it. Once a group matches
+/// the include patterns, so do the groups nested in it (`included`); groups which don't are still
+/// searched for nested groups that do, and only kept when they contain one.
+fn fetch_section_groups(client: &OnenoteClient, filter: &OnenoteFilter, urls: &mut Vec<String>, page_index: &mut usize, groups_path: &str, path: &[String], included: bool) -> Result<Vec<SectionGroupVO>> {
+    let (section_groups_url, section_groups) = client.list::<SectionGroup>(groups_path)?;
+
+    let mut section_group_vos = vec![];
+    for section_group in section_groups {
+        let section_group_name = section_group.display_name.unwrap_or_default();
+        if filter.section_groups.excludes(&section_group_name) {
+            continue;
+        }
+        let included = included || filter.section_groups.includes(&section_group_name);
 
-    let mut urls: Vec<String> = vec![];
+        let mut group_path = path.to_vec();
+        group_path.push(section_group_name.clone());
 
-    let mut page_index: usize = 1;
-    let mut onenote_vo = OnenoteVO::default();
-    match notebooks {
-        Ok(notebook) => {
-            let vec = notebook.body()["value"].as_array().unwrap();
-
-            for value in vec.iter() {
-                let notebook_id = value["id"].as_str().unwrap();
-                let book_name = value["displayName"].as_str().unwrap();
-
-                let mut notebook_vo = NotebookVO {
-                    sourceUrl: notebook.url().to_string(),
-                    id: notebook_id.to_string(),
-                    createdDateTime: value["createdDateTime"].as_str().unwrap().to_string(),
-                    displayName: book_name.to_string(),
-                    lastModifiedDateTime: value["lastModifiedDateTime"].as_str().unwrap().to_string(),
-                    sections: vec![],
-                };
-
-                println!("bookName: {:}", book_name.to_string());
-
-                let get_sections = client
-                    .v1()
-                    .user(user_id)
-                    .onenote()
-                    .notebook(notebook_id)
-                    .list_sections()
-                    .send();
-
-                match get_sections {
-                    Ok(section) => {
-                        let mut sections = build_sections(&client, user_id, &mut urls, section, &mut page_index);
-                        notebook_vo.sections.append(&mut sections);
-                    }
-                    Err(err) => {
-                        println!("{:?}", err);
-                    }
-                }
+        println!("  section group: {:}", group_path.join(" > "));
 
-                let mut section_groups_sections = fetch_sections_group_sections(&client, user_id, &mut urls, &mut page_index, notebook_id);
+        let mut section_group_vo = SectionGroupVO {
+            source_url: section_groups_url.clone(),
+            id: section_group.id,
+            created_date_time: section_group.created_date_time.unwrap_or_default(),
+            display_name: section_group_name,
+            last_modified_date_time: section_group.last_modified_date_time.unwrap_or_default(),
+            sections: vec![],
+            section_groups: vec![],
+        };
 
-                notebook_vo.sections.append(&mut section_groups_sections);
-                onenote_vo.notebooks.push(notebook_vo);
-            }
+        if included {
+            let (sections_url, sections) = client.list(&format!("/sectionGroups/{}/sections", section_group_vo.id))?;
+            section_group_vo.sections = build_sections(client, filter, urls, &sections_url, sections, page_index, &group_path)?;
         }
-        Err(err) => {
-            println!("{:?}", err);
+
+        let nested_path = format!("/sectionGroups/{}/sectionGroups", section_group_vo.id);
+        section_group_vo.section_groups = fetch_section_groups(client, filter, urls, page_index, &nested_path, &group_path, included)?;
+
+        if included || !section_group_vo.section_groups.is_empty() {
+            section_group_vos.push(section_group_vo);
         }
     }
 
-    let string = serde_json::to_string(&onenote_vo).unwrap();
-    fs::write("onenote-output.json", string).unwrap();
+    Ok(section_group_vos)
+}
+
+/// Build the `sections` listed at `sections_url` which pass the section filter, with their pages.
+///
+/// `path` holds the names of the notebook and section groups the sections are in, which the caller already filtered.
+fn build_sections(client: &OnenoteClient, filter: &OnenoteFilter, urls: &mut Vec<String>, sections_url: &str, sections: Vec<OnenoteSection>, index: &mut usize, path: &[String]) -> Result<Vec<SectionVO>> {
+    let mut section_vos: Vec<SectionVO> = vec![];
+    for section in sections {
+        let section_name = section.display_name.unwrap_or_default();
+
+        if !filter.sections.matches(&section_name) {
+            continue
+        }
+
+        println!("    sections name: {:}", section_name);
 
-    fs::write("urls", urls.join("\n")).unwrap();
+        let pages = fetch_pages(client, urls, index, &section.id)?;
+        println!("section's pages len: {:}", pages.len());
+
+        section_vos.push(SectionVO {
+            source_url: sections_url.to_string(),
+            id: section.id,
+            created_date_time: section.created_date_time.unwrap_or_default(),
+            display_name: section_name,
+            last_modified_date_time: section.last_modified_date_time.unwrap_or_default(),
+            parent_name: path.last().cloned().unwrap_or_default(),
+            path: path.to_vec(),
+            pages,
+        });
+    }
+
+    Ok(section_vos)
 }
 
-fn fetch_sections_group_sections(client: &Graph<BlockingHttpClient>, user_id: &str, mut urls: &mut Vec<String>, mut page_index: &mut usize, notebook_id: &str) -> Vec<SectionVO> {
-    let mut section_groups_sections = vec![];
-    let get_section_groups = client
-        .v1()
-        .user(user_id)
-        .onenote()
-        .notebook(notebook_id)
-        .list_section_groups()
-        .send();
-
-    match get_section_groups {
-        Ok(section) => {
-            let vec = section.body()["value"].as_array().unwrap();
-            for value in vec.iter() {
-                let section_group_id = value["id"].as_str().unwrap();
-
-                let get_sections = client
-                    .v1()
-                    .user(user_id)
-                    .onenote()
-                    .section_group(section_group_id)
-                    .list_sections()
-                    .send();
-
-                match get_sections {
-                    Ok(section) => {
-                        let mut sections = build_sections(&client, user_id, &mut urls, section, &mut page_index);
-                        section_groups_sections.append(&mut sections);
-                    }
-                    Err(err) => {
-                        println!("{:?}", err);
+/// Fetch the pages of the section `section_id`. Pages without a content url, which can't be downloaded, are left out.
+fn fetch_pages(client: &OnenoteClient, urls: &mut Vec<String>, index: &mut usize, section_id: &str) -> Result<Vec<PageVO>> {
+    let (pages_url, pages) = client.list::<OnenotePage>(&format!("/sections/{}/pages{}", section_id, client.page_query.to_query()))?;
+
+    let mut page_vos = vec![];
+    for page in pages {
+        let content_url = match page.content_url {
+            Some(content_url) => content_url,
+            None => continue,
+        };
+
+        urls.push(content_url.clone());
+
+        page_vos.push(PageVO {
+            source_url: pages_url.clone(),
+            id: page.id,
+            created_date_time: page.created_date_time.unwrap_or_default(),
+            last_modified_date_time: page.last_modified_date_time.unwrap_or_default(),
+            title: page.title.unwrap_or_default(),
+            content_url,
+        });
+        *index += 1;
+    }
+
+    Ok(page_vos)
+}
+
+/// The directory next to a downloaded page which the page's images and files are saved into.
+pub fn resources_dir_name(page_id: &str) -> String {
+    format!("{}_files", page_id)
+}
+
+/// The file in the download directory which records the downloaded pages.
+const MANIFEST_FILE: &str = "manifest.json";
+
+/// The directory in the download directory which pages removed upstream are moved into.
+const ARCHIVE_DIR: &str = "archive";
+
+/// What happens to downloaded pages which are no longer listed in the section index.
+#[derive(clap::ValueEnum, Clone, Copy, Debug)]
+pub enum RemovedPages {
+    /// Move them into the `archive` directory of the download directory.
+    Archive,
+
+    /// Delete them.
+    Delete,
+
+    /// Leave them where they are.
+    Keep,
+}
+
+/// Download the content of every page listed in the section index at `input` into `output_dir`,
+/// with up to `jobs` pages downloading at the same time.
+///
+/// Pages are only downloaded when they are new, their `lastModifiedDateTime` changed, or the local copy no
+/// longer matches the hash in the manifest. Pages downloaded earlier which are no longer in the index
+/// are handled as `removed` says. By default they are archived when the index lists every page of the user,
+/// and kept when it may not: when it was written with filters, or with `--flat`, which doesn't record them.
+///
+/// Every page is attempted according to the client's retry policy; the pages which still failed
+/// are reported at the end.
+pub fn download_pages(client: &GraphClient, input: &Path, output_dir: &Path, jobs: usize, removed: Option<RemovedPages>) -> Result<()> {
+    let sections = read_index(input)?;
+    let complete = is_complete_index(input)?;
+    let removed = removed.unwrap_or(if complete { RemovedPages::Archive } else { RemovedPages::Keep });
+
+    fs::create_dir_all(output_dir)?;
+    let manifest_path = output_dir.join(MANIFEST_FILE);
+    let manifest = Manifest::load(&manifest_path)?;
+
+    let all_pages: Vec<&PageVO> = sections.iter().flat_map(|section| &section.pages).collect();
+    let pages: Vec<&PageVO> = all_pages.iter()
+        .filter(|page| !is_up_to_date(output_dir, &manifest, page))
+        .copied()
+        .collect();
+
+    let manifest = Mutex::new(manifest);
+    let next = AtomicUsize::new(0);
+    let progress = Progress::new(pages.len());
+    let fails: Mutex<Vec<(String, Error)>> = Mutex::new(vec![]);
+
+    progress.print();
+    thread::scope(|scope| {
+        for _ in 0..jobs.max(1) {
+            scope.spawn(|| {
+                while let Some(page) = pages.get(next.fetch_add(1, Ordering::SeqCst)) {
+                    match download_page(client, &page.id, &page.content_url, output_dir) {
+                        Ok((path, sha256)) => {
+                            let entry = ManifestEntry {
+                                last_modified_date_time: page.last_modified_date_time.clone(),
+                                sha256,
+                                file: path.file_name().unwrap_or_default().to_string_lossy().to_string(),
+                            };
+                            manifest.lock().unwrap().pages.insert(page.id.clone(), entry);
+                            progress.succeed();
+                        }
+                        Err(err) => {
+                            fails.lock().unwrap().push((page.id.clone(), err));
+                            progress.fail();
+                        }
                     }
                 }
-            }
+            });
         }
-        Err(err) => {
-            println!("{:?}", err);
+    });
+
+    // The downloads are recorded before anything is removed, so that a failure while removing doesn't lose them.
+    let mut manifest = manifest.into_inner().unwrap();
+    manifest.save(&manifest_path)?;
+    let removed_count = remove_pages(output_dir, &mut manifest, &manifest_path, &all_pages, removed)?;
+
+    let fails = fails.into_inner().unwrap();
+    println!(
+        "{} pages downloaded, {} up to date, {} no longer in the index",
+        pages.len() - fails.len(), all_pages.len() - pages.len(), removed_count
+    );
+    if removed_count > 0 && !complete && matches!(removed, RemovedPages::Keep) {
+        println!("They were kept, as the index may leave out pages on purpose; use --removed to archive or delete them.");
+    }
+    if !fails.is_empty() {
+        println!("{} pages failed to download:", fails.len());
+        for (id, err) in &fails {
+            println!("    {}: {}", id, err);
         }
     }
-    section_groups_sections
+
+    Ok(())
 }
 
-fn build_sections(client: &Graph<BlockingHttpClient>, user_id: &str, urls: &mut Vec<String>, section: GraphResponse<Value>, index: &mut usize) -> Vec<SectionVO> {
-    let vec = section.body()["value"].as_array().unwrap();
-    let mut sections: Vec<SectionVO> = vec![];
-    for value in vec.iter() {
-        let section_id = value["id"].as_str().unwrap();
-        let section_name = value["displayName"].as_str().unwrap();
+/// Does the index at `path` list every page of the user? Only notebook trees written without filters do;
+/// flat indexes don't record whether they were filtered.
+fn is_complete_index(path: &Path) -> Result<bool> {
+    let index: Value = serde_json::from_str(&fs::read_to_string(path)?)?;
+    Ok(index.is_object() && !index["filtered"].as_bool().unwrap_or(false))
+}
 
-        if !(section_name == "文章") {
-            continue
+/// Read the sections of the index at `path`, which is either the notebook tree written by `onenote index`,
+/// or the list of sections written with `--flat`.
+pub fn read_index(path: &Path) -> Result<Vec<SectionVO>> {
+    let index: Value = serde_json::from_str(&fs::read_to_string(path)?)?;
+    if index.is_array() {
+        return Ok(serde_json::from_value(index)?);
+    }
+
+    let onenote: OnenoteVO = serde_json::from_value(index)?;
+    let mut sections = vec![];
+    for notebook in onenote.notebooks {
+        sections.extend(notebook.sections);
+        collect_sections(notebook.section_groups, &mut sections);
+    }
+    Ok(sections)
+}
+
+/// Move the sections of `section_groups`, and of the groups nested in them, into `sections`.
+fn collect_sections(section_groups: Vec<SectionGroupVO>, sections: &mut Vec<SectionVO>) {
+    for section_group in section_groups {
+        sections.extend(section_group.sections);
+        collect_sections(section_group.section_groups, sections);
+    }
+}
+
+/// Is the local copy of `page` in `output_dir` as recent as the index, and unchanged since it was downloaded?
+fn is_up_to_date(output_dir: &Path, manifest: &Manifest, page: &PageVO) -> bool {
+    match manifest.pages.get(&page.id) {
+        Some(entry) if entry.last_modified_date_time == page.last_modified_date_time => {
+            manifest::sha256_file(&output_dir.join(&entry.file)).map(|sha256| sha256 == entry.sha256).unwrap_or(false)
         }
+        _ => false,
+    }
+}
 
-        println!("    sections name: {:}", section_name);
+/// Archive or delete the downloaded pages in `manifest` which are not in `pages`, and drop them from the manifest,
+/// saving it at `manifest_path` after each page. Returns the number of such pages.
+fn remove_pages(output_dir: &Path, manifest: &mut Manifest, manifest_path: &Path, pages: &[&PageVO], removed: RemovedPages) -> Result<usize> {
+    let ids: HashSet<&str> = pages.iter().map(|page| page.id.as_str()).collect();
+    let gone: Vec<String> = manifest.pages.keys()
+        .filter(|id| !ids.contains(id.as_str()))
+        .cloned()
+        .collect();
+
+    // Kept pages stay in the manifest, so that they aren't downloaded again once they are back in the index.
+    if let RemovedPages::Keep = removed {
+        return Ok(gone.len());
+    }
 
-        let parentName = match value["parentSection"].as_object() {
-            None => {
-                "".to_string()
+    for id in &gone {
+        let names = [manifest.pages[id].file.clone(), resources_dir_name(id)];
+        let archive = archive_dir(output_dir, id, &names);
+        for name in &names {
+            let path = output_dir.join(name);
+            if !path.exists() {
+                continue;
             }
-            Some(obj) => {
-                match obj.get("displayName") {
-                    None => {
-                        "".to_string()
-                    }
-                    Some(name) => {
-                        name.as_str().unwrap().to_string()
-                    }
+
+            match removed {
+                RemovedPages::Archive => {
+                    fs::create_dir_all(&archive)?;
+                    fs::rename(&path, archive.join(name))?;
                 }
+                RemovedPages::Delete if path.is_dir() => fs::remove_dir_all(&path)?,
+                RemovedPages::Delete => fs::remove_file(&path)?,
+                RemovedPages::Keep => unreachable!(),
             }
-        };
+        }
 
-        let mut section_vo = SectionVO {
-            sourceUrl: section.url().to_string(),
-            id: section_id.to_string(),
-            createdDateTime: value["createdDateTime"].as_str().unwrap().to_string(),
-            displayName: section_name.to_string(),
-            lastModifiedDateTime: value["lastModifiedDateTime"].as_str().unwrap().to_string(),
-            parentName: parentName,
-            pages: vec![],
-        };
+        manifest.pages.remove(id);
+        manifest.save(manifest_path)?;
+    }
 
-        let mut skip: usize = 0;
-        let mut pages = fetch_pages(client, user_id, urls, index, section_id, &mut skip);
+    Ok(gone.len())
+}
+
+/// The directory the page `page_id`, saved as `names`, is archived into: the archive directory, or when an earlier
+/// copy of the page is already archived there, `{page_id} (2)`, `{page_id} (3)`, etc. within it. A page and its
+/// resources always go into the same directory, so that the links between them still work.
+fn archive_dir(output_dir: &Path, page_id: &str, names: &[String]) -> PathBuf {
+    let archive = output_dir.join(ARCHIVE_DIR);
+    let mut dir = archive.clone();
+    let mut n = 2;
+    while names.iter().any(|name| dir.join(name).exists()) {
+        dir = archive.join(files::safe_file_name(&format!("{} ({})", page_id, n)));
+        n += 1;
+    }
+    dir
+}
 
-        section_vo.pages.append(&mut pages);
+/// Download the content of the page `page_id` from its `content_url` into `output_dir/{page_id}.html`,
+/// returning the path and the SHA-256 of the content.
+///
+/// The images and files of the page are downloaded next to it, see `download_resources`.
+/// The content is written to a temporary file first, so that a failed download leaves an earlier copy intact.
+pub fn download_page(client: &GraphClient, page_id: &str, content_url: &str, output_dir: &Path) -> Result<(PathBuf, String)> {
+    let html = client.send(|http| http.get(content_url))?
+        .error_for_status()?
+        .text()?;
+    let html = download_resources(client, page_id, &html, output_dir)?;
+
+    let path = output_dir.join(format!("{:}.html", page_id));
+    let part = output_dir.join(format!("{:}.html.part", page_id));
+    let mut writer = HashingWriter::new(fs::File::create(&part)?);
+    writer.write_all(html.as_bytes())?;
+    let sha256 = writer.finish();
+    fs::rename(&part, &path)?;
+
+    Ok((path, sha256))
+}
+
+/// An image or file of a page, which Graph serves from `.../resources/{id}/$value`.
+struct PageResource {
+    url: String,
+    file_name: String,
+}
 
-        println!("section's pages len: {:}", section_vo.pages.len());
-        sections.push(section_vo);
+/// Download the resources referenced by the `img` and `object` elements of the page `page_id` into
+/// `output_dir/{page_id}_files`, and return `html` with their `src`, `data-fullres-src` and `data`
+/// attributes pointing to the local copies, relative to the page.
+fn download_resources(client: &GraphClient, page_id: &str, html: &str, output_dir: &Path) -> Result<String> {
+    let resources = page_resources(client, html);
+    if resources.is_empty() {
+        return Ok(html.to_string());
     }
 
-    sections
+    let dir_name = resources_dir_name(page_id);
+    let dir = output_dir.join(&dir_name);
+    fs::create_dir_all(&dir)?;
+
+    let mut html = html.to_string();
+    let mut used_paths = HashSet::new();
+    for resource in resources {
+        let mut response = client.send(|http| http.get(&resource.url))?
+            .error_for_status()?;
+
+        let path = files::unique_path(&dir, &resource.file_name, &mut used_paths);
+        response.copy_to(&mut fs::File::create(&path)?)?;
+
+        let local = format!("{}/{}", dir_name, path.file_name().unwrap_or_default().to_string_lossy());
+        html = html.replace(&resource.url, &local);
+    }
+
+    Ok(html)
 }
 
-fn fetch_pages(client: &Graph<BlockingHttpClient>, user_id: &str, urls: &mut Vec<String>, index: &mut usize, section_id: &str, skip: &mut usize) -> Vec<PageVO> {
-    let mut pages = vec![];
-
-    let get_pages = client
-        .v1()
-        .user(user_id)
-        .onenote()
-        .section(section_id)
-        .list_pages()
-        .skip(format!("{:}", skip).as_str())
-        .send();
-
-    match get_pages {
-        Ok(page) => {
-            let vec = page.body()["value"].as_array().unwrap();
-
-            for value in vec.iter() {
-                let page_id = value["id"].as_str().unwrap();
-                let content_url = value["contentUrl"].as_str().unwrap();
-
-                urls.push(content_url.clone().to_string());
-
-                let title = value["title"].as_str().unwrap().to_string();
-                let page_vo = PageVO {
-                    sourceUrl: page.url().to_string(),
-                    id: value["id"].as_str().unwrap().to_string(),
-                    createdDateTime: value["createdDateTime"].as_str().unwrap().to_string(),
-                    lastModifiedDateTime: value["lastModifiedDateTime"].as_str().unwrap().to_string(),
-                    title: title.clone(),
-                    contentUrl: content_url.to_string(),
-                };
-
-                // download_page(client, user_id, page_id);
-
-                pages.push(page_vo);
-                *index = *index + 1;
-            };
+/// The resources served by `client`'s Graph endpoint which are referenced by the images (`src` and
+/// `data-fullres-src`) and the objects (`data`) in `html`.
+///
+/// Urls on any other host are left out: they are requested with the user's access token, which must
+/// not reach a host pasted into a page. Files attached to the page keep their name (`data-attachment`);
+/// images are named after their resource id, with the extension of their `data-src-type`.
+fn page_resources(client: &GraphClient, html: &str) -> Vec<PageResource> {
+    let document = Html::parse_document(html);
+    let selector = Selector::parse("img, object").unwrap();
+
+    let mut seen = HashSet::new();
+    let mut resources = vec![];
+    for element in document.select(&selector) {
+        let element = element.value();
+        let attributes: &[(&str, &str)] = match element.name() {
+            "img" => &[("src", "data-src-type"), ("data-fullres-src", "data-fullres-src-type")],
+            _ => &[("data", "type")],
+        };
 
-            if page.body().next_link().is_some() {
-                *skip = *skip + 20;
-                let mut new_pages = fetch_pages(client, user_id, urls, index, section_id, skip);
-                pages.append(&mut new_pages);
+        for (url_attribute, type_attribute) in attributes {
+            let url = match element.attr(url_attribute) {
+                Some(url) if client.is_graph_url(url) => url,
+                _ => continue,
+            };
+            let id = match OnenoteResource::from_content_url(url) {
+                Some(resource) => resource.id,
+                None => continue,
+            };
+            if !seen.insert(url) {
+                continue;
             }
-        }
-        Err(err) => {
-            println!("{:?}", err);
+
+            let file_name = match (element.attr("data-attachment"), element.attr(type_attribute)) {
+                (Some(name), _) => name.to_string(),
+                (None, Some(media_type)) => format!("{}.{}", id, extension(media_type)),
+                (None, None) => id.to_string(),
+            };
+            resources.push(PageResource { url: url.to_string(), file_name });
         }
     }
-
-    pages
+    resources
 }
 
-pub fn download_page(client: &Graph<BlockingHttpClient>, user_id: &str, page_id: &str) -> Result<PathBuf, BlockingDownloadError> {
-    let download_page = client
-        .v1()
-        .user(user_id)
-        .onenote()
-        .page(page_id)
-        .download_page("./content");
-
-    download_page.rename(OsString::from(format!("{:}.html", page_id)));
-    let result = download_page.send();
-    result
+/// The file extension for `media_type`, e.g. `png` for `image/png` and `svg` for `image/svg+xml`.
+fn extension(media_type: &str) -> &str {
+    let subtype = media_type.split('/').nth(1).unwrap_or("bin");
+    match subtype.split('+').next().unwrap_or(subtype) {
+        "jpeg" => "jpg",
+        subtype => subtype,
+    }
 }
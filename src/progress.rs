@@ -0,0 +1,43 @@
+use std::io::{self, Write};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// Counts the items of a long running job which are done or failed, and prints the counts
+/// on a single, continuously updated line.
+pub struct Progress {
+    total: usize,
+    done: AtomicUsize,
+    failed: AtomicUsize,
+}
+
+impl Progress {
+    pub fn new(total: usize) -> Self {
+        Self {
+            total,
+            done: AtomicUsize::new(0),
+            failed: AtomicUsize::new(0),
+        }
+    }
+
+    pub fn succeed(&self) {
+        self.done.fetch_add(1, Ordering::SeqCst);
+        self.print();
+    }
+
+    pub fn fail(&self) {
+        self.failed.fetch_add(1, Ordering::SeqCst);
+        self.print();
+    }
+
+    /// Print the current counts, ending the line once every item is accounted for.
+    pub fn print(&self) {
+        let done = self.done.load(Ordering::SeqCst);
+        let failed = self.failed.load(Ordering::SeqCst);
+        let remaining = self.total.saturating_sub(done + failed);
+
+        print!("\rdone: {}, failed: {}, remaining: {}", done, failed, remaining);
+        if remaining == 0 {
+            println!();
+        }
+        let _ = io::stdout().flush();
+    }
+}
@@ -0,0 +1,312 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+use quake_microsoft_todo::tasks::BodyType;
+
+use crate::error::{Error, Result};
+use crate::onenote::read_index;
+use crate::{markdown, vault, OutputList};
+
+/// The index of the entries of an entry type directory.
+const ENTRIES_CSV: &str = "entries.csv";
+
+/// A Quake entry to write: the front matter fields besides its number, and its Markdown content.
+struct Entry {
+    /// The Graph id of the page or task, by which entries written earlier are recognized.
+    source_id: String,
+    title: String,
+    created_date: String,
+    updated_date: String,
+
+    /// Further front matter fields, written between the dates and the source id.
+    fields: Vec<(&'static str, String)>,
+    content: String,
+}
+
+/// Write the pages listed in the index at `input`, as downloaded into `content_dir`, as entries of `entry_type`
+/// in the Quake workspace `output_dir`. Their images and files are copied into the `assets` of the entry type.
+pub fn write_onenote_entries(input: &Path, content_dir: &Path, output_dir: &Path, entry_type: &str) -> Result<()> {
+    let dir = output_dir.join(entry_type);
+    fs::create_dir_all(&dir)?;
+
+    let mut entries = vec![];
+    for section in read_index(input)? {
+        for page in section.pages {
+            let html_path = content_dir.join(format!("{}.html", page.id));
+            if !html_path.exists() {
+                continue;
+            }
+
+            let html = vault::copy_assets(&fs::read_to_string(&html_path)?, content_dir, &dir, &page.id)?;
+            entries.push(Entry {
+                source_id: page.id,
+                title: page.title,
+                created_date: quake_date(&page.created_date_time),
+                updated_date: quake_date(&page.last_modified_date_time),
+                fields: vec![],
+                content: markdown::convert_page(&html),
+            });
+        }
+    }
+
+    write_entries(&dir, entries)
+}
+
+/// Write the tasks of the JSON export at `input` as entries of `entry_type` in the Quake workspace `output_dir`.
+pub fn write_todo_entries(input: &Path, output_dir: &Path, entry_type: &str) -> Result<()> {
+    let dir = output_dir.join(entry_type);
+    fs::create_dir_all(&dir)?;
+
+    let lists: Vec<OutputList> = serde_json::from_str(&fs::read_to_string(input)?)?;
+    let mut entries = vec![];
+    for list in lists {
+        for task in list.children {
+            let mut content = match task.body.content_type {
+                BodyType::Html => markdown::convert_page(&task.body.content),
+                _ => format!("{}\n", task.body.content.trim()),
+            };
+            for item in &task.checklist_items {
+                let checkbox = if item.is_checked { "[x]" } else { "[ ]" };
+                content.push_str(&format!("- {} {}\n", checkbox, item.display_name));
+            }
+
+            let status = serde_json::to_value(&task.status)?.as_str().unwrap_or_default().to_string();
+            entries.push(Entry {
+                source_id: task.id,
+                title: task.title,
+                created_date: quake_date(&task.created_date_time),
+                updated_date: quake_date(&task.last_modified_date_time),
+                fields: vec![("list", list.display_name.clone()), ("status", status)],
+                content: content.trim_start().to_string(),
+            });
+        }
+    }
+
+    write_entries(&dir, entries)
+}
+
+/// Write `entries` into the entry type directory `dir` as `0001-title.md` files, numbered after the entries already
+/// there, and list them in its `entries.csv`. An entry whose source id is already in the directory is written over
+/// the earlier one instead, keeping its number and its row, so that running the export again brings every entry up
+/// to date and only adds what's new.
+///
+/// Fails when the `entries.csv` of `dir` has other columns than `entries`, as those of pages and tasks differ.
+fn write_entries(dir: &Path, entries: Vec<Entry>) -> Result<()> {
+    let (mut last_id, mut written) = (0, HashMap::new());
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.extension().is_none_or(|extension| extension != "md") {
+            continue;
+        }
+        let name = path.file_name().unwrap_or_default().to_string_lossy().to_string();
+        let id = match name.split('-').next().and_then(|id| id.parse::<usize>().ok()) {
+            Some(id) => id,
+            None => continue,
+        };
+        last_id = last_id.max(id);
+        if let Some(source_id) = front_matter_field(&fs::read_to_string(&path)?, "source_id") {
+            written.insert(source_id, (id, path));
+        }
+    }
+
+    let csv_path = dir.join(ENTRIES_CSV);
+    let mut csv = EntriesCsv::read(&csv_path)?;
+    if let Some(entry) = entries.first() {
+        let header = Some("id").into_iter().chain(entry.field_names()).collect::<Vec<_>>().join(",");
+        match &csv.header {
+            Some(existing) if *existing != header => {
+                return Err(Error::QuakeError(format!(
+                    "{} has the columns {}, but these entries have {}; write them into another entry type",
+                    csv_path.display(), existing, header
+                )));
+            }
+            Some(_) => {}
+            None => csv.header = Some(header),
+        }
+    }
+
+    let (mut added, mut updated, mut unchanged) = (0, 0, 0);
+    for entry in entries {
+        let fields = entry.fields();
+        let mut text = String::from("---\n");
+        for (name, value) in &fields {
+            text.push_str(&format!("{}: {}\n", name, yaml_value(value)));
+        }
+        text.push_str("---\n\n");
+        text.push_str(&entry.content);
+
+        let id = match written.get(&entry.source_id) {
+            Some((id, path)) => {
+                let new_path = dir.join(file_name(*id, &entry.title));
+                if *path == new_path && fs::read_to_string(path)? == text {
+                    unchanged += 1;
+                    continue;
+                }
+                if *path != new_path {
+                    fs::remove_file(path)?;
+                }
+                updated += 1;
+                *id
+            }
+            None => {
+                last_id += 1;
+                added += 1;
+                last_id
+            }
+        };
+
+        let path = dir.join(file_name(id, &entry.title));
+        fs::write(&path, text)?;
+        csv.set_row(id, Some(id.to_string()).into_iter().chain(fields.iter().map(|(_, value)| csv_value(value))).collect::<Vec<_>>().join(","));
+        written.insert(entry.source_id, (id, path));
+    }
+
+    // The index is only created once there is an entry to list in it.
+    if added + updated > 0 {
+        csv.write(&csv_path)?;
+    }
+
+    println!("{} entries written into {}, {} updated, {} unchanged", added, dir.display(), updated, unchanged);
+    Ok(())
+}
+
+impl Entry {
+    /// The front matter fields of the entry, in the order they are written.
+    fn fields(&self) -> Vec<(&'static str, String)> {
+        let mut fields = vec![
+            ("title", self.title.clone()),
+            ("created_date", self.created_date.clone()),
+            ("updated_date", self.updated_date.clone()),
+        ];
+        fields.extend(self.fields.iter().cloned());
+        fields.push(("source_id", self.source_id.clone()));
+        fields
+    }
+
+    /// The names of the `fields`, which are the columns of the entry in `entries.csv` besides its number.
+    fn field_names(&self) -> Vec<&'static str> {
+        self.fields().into_iter().map(|(name, _)| name).collect()
+    }
+}
+
+/// The `entries.csv` of an entry type directory: its header, and a row per entry starting with the entry number.
+struct EntriesCsv {
+    header: Option<String>,
+    rows: Vec<String>,
+}
+
+impl EntriesCsv {
+    /// Read the index at `path`, or start an empty one when there is none.
+    fn read(path: &Path) -> Result<Self> {
+        let text = if path.exists() { fs::read_to_string(path)? } else { String::new() };
+        let mut records = csv_records(&text).into_iter();
+        Ok(EntriesCsv { header: records.next(), rows: records.collect() })
+    }
+
+    /// Set the row of the entry `id` to `row`, adding it when the entry has none yet.
+    fn set_row(&mut self, id: usize, row: String) {
+        let prefix = format!("{},", id);
+        match self.rows.iter_mut().find(|existing| existing.starts_with(&prefix)) {
+            Some(existing) => *existing = row,
+            None => self.rows.push(row),
+        }
+    }
+
+    fn write(&self, path: &Path) -> Result<()> {
+        let mut text = String::new();
+        for record in self.header.iter().chain(&self.rows) {
+            text.push_str(record);
+            text.push('\n');
+        }
+        fs::write(path, text)?;
+        Ok(())
+    }
+}
+
+/// The records of the CSV `text`, as written by `csv_value`: lines, except for line breaks within quotes.
+fn csv_records(text: &str) -> Vec<String> {
+    let (mut records, mut record, mut quoted) = (vec![], String::new(), false);
+    for c in text.chars() {
+        match c {
+            '"' => quoted = !quoted,
+            '\n' if !quoted => {
+                records.push(record.trim_end_matches('\r').to_string());
+                record.clear();
+                continue;
+            }
+            _ => {}
+        }
+        record.push(c);
+    }
+    if !record.is_empty() {
+        records.push(record);
+    }
+    records.retain(|record| !record.is_empty());
+    records
+}
+
+/// The file name of the entry `id`, e.g. `0001-buy-milk.md`.
+fn file_name(id: usize, title: &str) -> String {
+    let mut slug = String::new();
+    for c in title.chars().flat_map(|c| c.to_lowercase()) {
+        if c.is_alphanumeric() {
+            slug.push(c);
+        } else if !slug.is_empty() && !slug.ends_with('-') {
+            slug.push('-');
+        }
+    }
+
+    // Keep the name well below the file name limit of most file systems.
+    let slug: String = slug.trim_end_matches('-').chars().take(80).collect();
+    let slug = slug.trim_end_matches('-');
+    if slug.is_empty() {
+        format!("{:04}-untitled.md", id)
+    } else {
+        format!("{:04}-{}.md", id, slug)
+    }
+}
+
+/// The value of the front matter field `name` of the entry `content`, as written by `write_entries`.
+fn front_matter_field(content: &str, name: &str) -> Option<String> {
+    let prefix = format!("{}: ", name);
+    content.lines()
+        .skip(1)
+        .take_while(|line| *line != "---")
+        .find_map(|line| line.strip_prefix(&prefix))
+        .map(|value| serde_json::from_str(value).unwrap_or_else(|_| value.to_string()))
+}
+
+/// A Graph timestamp such as `2021-01-04T10:00:00.0000000Z` in the `2021-01-04 10:00:00` format of Quake.
+fn quake_date(timestamp: &str) -> String {
+    timestamp.chars().take(19).map(|c| if c == 'T' { ' ' } else { c }).collect()
+}
+
+/// `value` as a YAML scalar: dates are written as they are, like Quake does, anything else is quoted.
+fn yaml_value(value: &str) -> String {
+    if is_quake_date(value) {
+        value.to_string()
+    } else {
+        // JSON strings are valid YAML, and escape whatever the value may hold.
+        serde_json::to_string(value).unwrap_or_default()
+    }
+}
+
+/// Is `value` a date as written by `quake_date`?
+fn is_quake_date(value: &str) -> bool {
+    value.len() == 19 && value.chars().enumerate().all(|(i, c)| match i {
+        4 | 7 => c == '-',
+        10 => c == ' ',
+        13 | 16 => c == ':',
+        _ => c.is_ascii_digit(),
+    })
+}
+
+/// `value` as a CSV field, quoted when it holds a comma, a quote or a line break.
+fn csv_value(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
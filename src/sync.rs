@@ -0,0 +1,127 @@
+use std::collections::{BTreeMap, HashSet};
+use std::fs;
+use std::path::Path;
+
+use quake_microsoft_todo::tasks::{TodoTask, TodoTaskList};
+use quake_microsoft_todo::Delta;
+
+use crate::error::Result;
+use crate::files::JsonFile;
+use crate::http::GraphClient;
+use crate::{todo, OutputList};
+
+/// The delta links returned by the last sync, from which the next sync requests only what changed since.
+#[derive(Serialize, Deserialize, Debug, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct SyncState {
+    /// The delta link of the lists.
+    pub lists: Option<String>,
+
+    /// The delta link of the tasks of each list, by list id.
+    pub tasks: BTreeMap<String, String>,
+}
+
+impl JsonFile for SyncState {}
+
+/// Bring the JSON export at `output_path` up to date with Graph delta queries, requesting only the lists
+/// and tasks which were added, changed or removed since the delta links saved in `state_path`.
+///
+/// Without a saved delta link, every list or task is requested and anything else in the export is dropped.
+/// The saved delta links are only used along with the export they were saved with: when it is missing or
+/// can't be read, everything is requested again.
+/// The attachments of added and changed tasks are downloaded into `attachments_dir` if given.
+pub fn sync_todos(client: &GraphClient, output_path: &Path, state_path: &Path, attachments_dir: Option<&Path>) -> Result<()> {
+    let (mut lists, mut state) = match read_export(output_path) {
+        Ok(lists) => (lists, SyncState::load(state_path)?),
+        Err(e) => {
+            if state_path.exists() {
+                println!("{} can't be read ({}), syncing everything again", output_path.display(), e);
+            }
+            (vec![], SyncState::default())
+        }
+    };
+
+    let initial = state.lists.is_none();
+    let url = state.lists.clone().unwrap_or_else(|| client.url("/me/todo/lists/delta"));
+    let mut seen = HashSet::new();
+    let mut pager = client.pages::<Delta<TodoTaskList>>(&url);
+    for item in pager.by_ref() {
+        match item? {
+            Delta::Removed { id, .. } => {
+                println!("removed list: {}", id);
+                lists.retain(|list| list.id != id);
+                state.tasks.remove(&id);
+            }
+            Delta::Changed(list) => {
+                seen.insert(list.id.clone());
+                match lists.iter_mut().find(|existing| existing.id == list.id) {
+                    Some(existing) => {
+                        existing.display_name = list.display_name;
+                        existing.wellknown_list_name = list.wellknown_list_name;
+                    }
+                    None => lists.push(OutputList {
+                        display_name: list.display_name,
+                        id: list.id,
+                        wellknown_list_name: list.wellknown_list_name,
+                        children: vec![],
+                    }),
+                }
+            }
+        }
+    }
+    if initial {
+        lists.retain(|list| seen.contains(&list.id));
+    }
+    state.lists = pager.delta_link().map(|link| link.to_string());
+
+    for list in lists.iter_mut() {
+        let (mut added, mut changed) = (0, 0);
+        let before = list.children.len();
+
+        let initial = !state.tasks.contains_key(&list.id);
+        let url = state.tasks.get(&list.id).cloned()
+            .unwrap_or_else(|| client.url(&format!("/me/todo/lists/{}/tasks/delta", list.id)));
+        let mut seen = HashSet::new();
+        let mut pager = client.pages::<Delta<TodoTask>>(&url);
+        for item in pager.by_ref() {
+            match item? {
+                Delta::Removed { id, .. } => list.children.retain(|task| task.id != id),
+                Delta::Changed(mut task) => {
+                    todo::fetch_task_relations(client, &list.id, &mut task)?;
+                    todo::fetch_attachments(client, &list.id, &list.display_name, &mut task, attachments_dir)?;
+                    seen.insert(task.id.clone());
+                    match list.children.iter().position(|existing| existing.id == task.id) {
+                        Some(i) => {
+                            list.children[i] = task;
+                            changed += 1;
+                        }
+                        None => {
+                            list.children.push(task);
+                            added += 1;
+                        }
+                    }
+                }
+            }
+        }
+        if initial {
+            list.children.retain(|task| seen.contains(&task.id));
+        }
+        if let Some(link) = pager.delta_link() {
+            state.tasks.insert(list.id.clone(), link.to_string());
+        }
+
+        let removed = before + added - list.children.len();
+        println!("{}: {} added, {} changed, {} removed", list.display_name, added, changed, removed);
+    }
+
+    // The export is written before the state, so that a failed write is synced again next time.
+    fs::write(output_path, serde_json::to_string(&lists)?)?;
+    state.save(state_path)?;
+
+    Ok(())
+}
+
+/// Read the JSON export at `path`.
+fn read_export(path: &Path) -> Result<Vec<OutputList>> {
+    Ok(serde_json::from_str(&fs::read_to_string(path)?)?)
+}
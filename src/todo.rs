@@ -1,39 +1,149 @@
-use quake_microsoft_todo::Collection;
+use quake_microsoft_todo::tasks::{ChecklistItem, Extension, LinkedResource, TaskFileAttachment, TodoTask, TodoTaskList};
+use std::collections::HashSet;
 use std::fs;
-use crate::{CollectionReader, error, OutputList};
+use std::path::Path;
+use crate::{error, files, manifest, OutputList};
+use crate::http::GraphClient;
 
-fn dump_todos(token: &str) -> error::Result<()> {
-    let client = reqwest::blocking::Client::new();
+/// The relations of a task which Graph only returns along with it when they are expanded.
+pub const TASK_RELATIONS: &str = "checklistItems,extensions,linkedResources";
 
-    let lists: Collection<quake_microsoft_todo::tasks::TodoTaskList> = client.get(crate::graph_url("/me/todo/lists"))
-        .bearer_auth(token)
-        .send()?
-        .json()?;
+/// What `fetch_lists` fetches along with the tasks.
+#[derive(Clone, Copy, Debug)]
+pub enum TaskDetails<'a> {
+    /// Only their checklist items, which is all an import recreates.
+    Checklist,
+
+    /// Every relation and attachment, downloading the attachments into the given directory, if any.
+    All { attachments_dir: Option<&'a Path> },
+}
+
+/// The formats a To Do export can be written in.
+#[derive(clap::ValueEnum, Clone, Copy, Debug)]
+pub enum ExportFormat {
+    /// Every list, with all task properties, as JSON.
+    Json,
+
+    /// The task titles of every list, as plain text.
+    Text,
+}
+
+/// Export every To Do list, with its tasks, into `output_path`.
+///
+/// When an `attachments_dir` is given, the files attached to the tasks are downloaded into
+/// `attachments_dir/{list}/{task}/`, see `attachments_dir_name`.
+pub fn dump_todos(client: &GraphClient, output_path: &Path, format: ExportFormat, attachments_dir: Option<&Path>) -> error::Result<()> {
+    let output = fetch_lists(client, TaskDetails::All { attachments_dir })?;
+
+    let string = match format {
+        ExportFormat::Json => serde_json::to_string(&output)?,
+        ExportFormat::Text => to_text(&output),
+    };
+    fs::write(output_path, string)?;
+
+    Ok(())
+}
+
+/// Fetch every To Do list along with its tasks, and the `details` of those.
+pub fn fetch_lists(client: &GraphClient, details: TaskDetails) -> error::Result<Vec<OutputList>> {
+    let expand = match details {
+        TaskDetails::Checklist => "checklistItems",
+        TaskDetails::All { .. } => TASK_RELATIONS,
+    };
 
     let mut output: Vec<OutputList> = vec![];
-    for list in lists.value.iter() {
-        let fetch_url = crate::graph_url(&format!("/me/todo/lists/{}/tasks", &list.id));
-
-        let mut task_collection = CollectionReader::<quake_microsoft_todo::tasks::TodoTask>::new(&client, &token);
-        task_collection.fetch(fetch_url)?;
-
-        let mut list1 = OutputList {
-            display_name: list.display_name.to_string(),
-            id: list.id.to_string(),
-            wellknown_list_name: list.wellknown_list_name.clone(),
-            children: task_collection.items.clone(),
-        };
-
-        while task_collection.has_next_link() {
-            let _ = task_collection.fetch_next();
-            list1.children.append(&mut task_collection.items.clone());
+    for list in client.pages::<TodoTaskList>(&client.url("/me/todo/lists")) {
+        let list = list?;
+        let tasks_url = client.url(&format!("/me/todo/lists/{}/tasks?$expand={}", &list.id, expand));
+
+        let mut tasks: Vec<TodoTask> = client.pages(&tasks_url).collect::<error::Result<_>>()?;
+        if let TaskDetails::All { attachments_dir } = details {
+            for task in tasks.iter_mut() {
+                fetch_attachments(client, &list.id, &list.display_name, task, attachments_dir)?;
+            }
         }
 
-        output.push(list1);
+        output.push(OutputList {
+            display_name: list.display_name,
+            id: list.id,
+            wellknown_list_name: list.wellknown_list_name,
+            children: tasks,
+        });
     }
 
-    let string = serde_json::to_string(&output).unwrap();
-    fs::write("output.json", string).unwrap();
+    Ok(output)
+}
 
+/// The relations of a task, as returned when they are expanded.
+#[derive(Deserialize, Debug, Default)]
+#[serde(rename_all = "camelCase", default)]
+struct TaskRelations {
+    checklist_items: Vec<ChecklistItem>,
+    extensions: Vec<Extension>,
+    linked_resources: Vec<LinkedResource>,
+}
+
+/// Fetch the `TASK_RELATIONS` of `task` in the list `list_id`, for tasks listed without them, as delta queries do,
+/// which can't expand them.
+pub fn fetch_task_relations(client: &GraphClient, list_id: &str, task: &mut TodoTask) -> error::Result<()> {
+    let url = client.url(&format!("/me/todo/lists/{}/tasks/{}?$expand={}", list_id, task.id, TASK_RELATIONS));
+    let relations: TaskRelations = client.get_json(&url)?;
+    task.checklist_items = relations.checklist_items;
+    task.extensions = relations.extensions;
+    task.linked_resources = relations.linked_resources;
     Ok(())
 }
+
+/// Fetch the attachments of `task` in the list `list_id`, which Graph doesn't return with the task itself,
+/// downloading them into `attachments_dir` if given.
+pub fn fetch_attachments(client: &GraphClient, list_id: &str, list_name: &str, task: &mut TodoTask, attachments_dir: Option<&Path>) -> error::Result<()> {
+    let task_url = client.url(&format!("/me/todo/lists/{}/tasks/{}", list_id, task.id));
+    if task.has_attachments {
+        task.attachments = client.pages(&format!("{}/attachments", task_url)).collect::<error::Result<_>>()?;
+    }
+
+    if let Some(dir) = attachments_dir.filter(|_| !task.attachments.is_empty()) {
+        let list_dir = dir.join(files::safe_file_name(list_name));
+        let task_dir = list_dir.join(files::safe_file_name(&attachments_dir_name(task)));
+        download_attachments(client, &task_url, &task.attachments, &task_dir)?;
+    }
+
+    Ok(())
+}
+
+/// The directory of the attachments of `task` in the directory of its list: its title, followed by a short hash
+/// of its id. Tasks with the same title so get their own directory, and a task gets the same one on every export
+/// and sync, whichever other tasks were fetched along with it.
+fn attachments_dir_name(task: &TodoTask) -> String {
+    format!("{} ({})", task.title.trim(), &manifest::sha256(task.id.as_bytes())[..8])
+}
+
+/// Download the content of every attachment of the task at `task_url` into `dir`, streaming it to disk.
+fn download_attachments(client: &GraphClient, task_url: &str, attachments: &[TaskFileAttachment], dir: &Path) -> error::Result<()> {
+    fs::create_dir_all(dir)?;
+
+    let mut used_paths = HashSet::new();
+    for attachment in attachments {
+        let url = format!("{}/attachments/{}/$value", task_url, attachment.id);
+        let mut response = client.send(|http| http.get(&url))?
+            .error_for_status()?;
+
+        let mut file = fs::File::create(files::unique_path(dir, &attachment.name, &mut used_paths))?;
+        response.copy_to(&mut file)?;
+    }
+
+    Ok(())
+}
+
+/// Render the title of every task, grouped under the name of its list.
+fn to_text(lists: &[OutputList]) -> String {
+    let mut text = String::new();
+    for list in lists {
+        text.push_str(&format!("# {}\n", list.display_name));
+        for task in &list.children {
+            text.push_str(&format!("{}\n", task.title));
+        }
+        text.push('\n');
+    }
+    text
+}
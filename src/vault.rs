@@ -0,0 +1,81 @@
+use std::collections::HashSet;
+use std::fs;
+use std::path::Path;
+
+use crate::error::Result;
+use crate::files;
+use crate::onenote::{self, read_index};
+use crate::{markdown, PageVO};
+
+/// The directory of each section which the images and files of its pages are copied into, one directory per page.
+const ASSETS_DIR: &str = "assets";
+
+/// Write the pages listed in the index at `input`, as downloaded into `content_dir`, into `output_dir` as Markdown:
+/// one directory per notebook, section group and section, and one file per page named after its title.
+///
+/// Each page starts with front matter holding its id, title and timestamps. Its images and files are copied into
+/// `assets/{page id}` next to it. Pages which weren't downloaded are left out.
+pub fn export_vault(input: &Path, content_dir: &Path, output_dir: &Path) -> Result<()> {
+    let sections = read_index(input)?;
+
+    let mut used_paths = HashSet::new();
+    let (mut written, mut missing) = (0, 0);
+    for section in &sections {
+        let mut dir = output_dir.to_path_buf();
+        for name in section.path.iter().chain(Some(&section.display_name)) {
+            dir.push(files::safe_file_name(name));
+        }
+
+        for page in &section.pages {
+            let html_path = content_dir.join(format!("{}.html", page.id));
+            if !html_path.exists() {
+                missing += 1;
+                continue;
+            }
+
+            fs::create_dir_all(&dir)?;
+            let title = if page.title.trim().is_empty() { "Untitled" } else { page.title.trim() };
+            let path = files::unique_path(&dir, &format!("{}.md", title), &mut used_paths);
+
+            let html = fs::read_to_string(&html_path)?;
+            let html = copy_assets(&html, content_dir, &dir, &page.id)?;
+            fs::write(&path, format!("{}{}", front_matter(page), markdown::convert_page(&html)))?;
+            written += 1;
+        }
+    }
+
+    println!("{} pages written into {}, {} not downloaded", written, output_dir.display(), missing);
+    Ok(())
+}
+
+/// The YAML front matter of `page`, including the closing line.
+fn front_matter(page: &PageVO) -> String {
+    // JSON strings are valid YAML, and escape whatever the title may hold.
+    let title = serde_json::to_string(&page.title).unwrap_or_default();
+    format!(
+        "---\nid: {}\ntitle: {}\ncreated: {}\nmodified: {}\n---\n\n",
+        page.id, title, page.created_date_time, page.last_modified_date_time
+    )
+}
+
+/// Copy the images and files downloaded with the page `page_id` into the assets directory of `dir`, and
+/// return `html` with its links to them pointing to the copies.
+pub fn copy_assets(html: &str, content_dir: &Path, dir: &Path, page_id: &str) -> Result<String> {
+    let resources_dir_name = onenote::resources_dir_name(page_id);
+    let resources = content_dir.join(&resources_dir_name);
+    if !resources.is_dir() {
+        return Ok(html.to_string());
+    }
+
+    let assets = dir.join(ASSETS_DIR).join(files::safe_file_name(page_id));
+    fs::create_dir_all(&assets)?;
+    for entry in fs::read_dir(&resources)? {
+        let entry = entry?;
+        if entry.file_type()?.is_file() {
+            fs::copy(entry.path(), assets.join(entry.file_name()))?;
+        }
+    }
+
+    let local = format!("{}/{}/", ASSETS_DIR, files::safe_file_name(page_id));
+    Ok(html.replace(&format!("=\"{}/", resources_dir_name), &format!("=\"{}", local)))
+}
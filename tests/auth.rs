@@ -0,0 +1,39 @@
+mod common;
+
+use std::fs;
+
+use common::{json, token, GraphStub};
+
+#[test]
+fn a_rejected_access_token_is_refreshed_once() {
+    let stub = GraphStub::start(vec![
+        token("/common/oauth2/v2.0/token", "auth/refreshed-token.json"),
+        json("/v1.0/me/todo/lists", "todo/empty.json"),
+    ]);
+    let dir = tempfile::tempdir().unwrap();
+    fs::copy(common::fixture_path("auth/token-cache.json"), dir.path().join("tokens.json")).unwrap();
+
+    stub.run(dir.path(), &["todo", "export", "--token-cache", "tokens.json", "--authority", &stub.base_url]);
+
+    assert_eq!(
+        stub.requests(),
+        vec!["/v1.0/me/todo/lists", "/common/oauth2/v2.0/token", "/v1.0/me/todo/lists"],
+        "the rejected request is sent again with the refreshed token"
+    );
+    let posts = stub.posts();
+    let form = posts[0].1.as_str().unwrap();
+    assert!(form.contains("grant_type=refresh_token"));
+    assert!(form.contains("refresh_token=refresh-token-1"));
+
+    let cache = common::read_json(&dir.path().join("tokens.json"));
+    assert_eq!(cache["accessToken"], common::TOKEN);
+    assert_eq!(cache["refreshToken"], "refresh-token-2");
+    assert_eq!(common::read_json(&dir.path().join("output.json")), serde_json::json!([]));
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        let mode = fs::metadata(dir.path().join("tokens.json")).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o600, "only the user may read the refresh token");
+    }
+}
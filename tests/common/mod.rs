@@ -0,0 +1,239 @@
+#![allow(dead_code)]
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output};
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+use tiny_http::{Header, Response, Server};
+
+/// The token passed to every command run against the stub.
+pub const TOKEN: &str = "stub-token";
+
+/// A canned response of the stub server.
+#[derive(Clone)]
+pub struct Route {
+    /// The method of the request, e.g. `GET`.
+    pub method: &'static str,
+
+    /// The path and query of the request, e.g. `/v1.0/me/todo/lists`.
+    pub url: String,
+
+    /// The fixture file served for the request, relative to `tests/fixtures`.
+    pub fixture: String,
+
+    pub status: u16,
+
+    pub content_type: &'static str,
+
+    /// Further headers of the response, e.g. `Retry-After`.
+    pub headers: Vec<(&'static str, &'static str)>,
+
+    /// How many requests the route answers before leaving them to the next route for the same url, if limited.
+    pub times: Option<usize>,
+
+    /// Whether the request must carry the stub's access token, as Graph requests do.
+    pub authorized: bool,
+}
+
+impl Route {
+    /// Answer with the header `name` set to `value` as well.
+    pub fn header(mut self, name: &'static str, value: &'static str) -> Self {
+        self.headers.push((name, value));
+        self
+    }
+
+    /// Only answer the first `times` requests.
+    pub fn times(self, times: usize) -> Self {
+        Route { times: Some(times), ..self }
+    }
+}
+
+/// Serve the JSON `fixture` for `url`.
+pub fn json(url: &str, fixture: &str) -> Route {
+    Route {
+        method: "GET",
+        url: url.to_string(),
+        fixture: fixture.to_string(),
+        status: 200,
+        content_type: "application/json",
+        headers: vec![],
+        times: None,
+        authorized: true,
+    }
+}
+
+/// Answer `GET` requests for `url` with `status` and the JSON error `fixture`.
+pub fn error(url: &str, status: u16, fixture: &str) -> Route {
+    Route {
+        status,
+        ..json(url, fixture)
+    }
+}
+
+/// Answer a `POST` to `url` with the JSON `fixture`, as the created resource.
+pub fn post(url: &str, fixture: &str) -> Route {
+    Route {
+        method: "POST",
+        status: 201,
+        ..json(url, fixture)
+    }
+}
+
+/// Answer a token request to the identity platform at `url` with the JSON `fixture`.
+pub fn token(url: &str, fixture: &str) -> Route {
+    Route {
+        authorized: false,
+        ..post(url, fixture)
+    }
+}
+
+/// Serve the HTML `fixture` for `url`.
+pub fn html(url: &str, fixture: &str) -> Route {
+    Route {
+        content_type: "text/html",
+        ..json(url, fixture)
+    }
+}
+
+/// Serve the binary `fixture` for `url`.
+pub fn file(url: &str, fixture: &str) -> Route {
+    Route {
+        content_type: "application/octet-stream",
+        ..json(url, fixture)
+    }
+}
+
+/// Serve tasks without checklist items, extensions or linked resources for each `(list, task)` of `tasks`.
+pub fn empty_task_relations(tasks: &[(&str, &str)]) -> Vec<Route> {
+    tasks.iter()
+        .map(|(list, task)| {
+            let url = format!("/v1.0/me/todo/lists/{}/tasks/{}?$expand=checklistItems,extensions,linkedResources", list, task);
+            json(&url, "todo/empty.json")
+        })
+        .collect()
+}
+
+/// An in-process HTTP server standing in for Microsoft Graph.
+///
+/// Fixtures may contain `{{base}}`, which is replaced with the server's base url so that
+/// `@odata.nextLink` and `contentUrl` links lead back to the stub, and `{{port}}`, its port.
+pub struct GraphStub {
+    pub base_url: String,
+    server: Arc<Server>,
+    requests: Arc<Mutex<Vec<String>>>,
+    posts: Arc<Mutex<Vec<(String, serde_json::Value)>>>,
+}
+
+impl GraphStub {
+    pub fn start(routes: Vec<Route>) -> Self {
+        let server = Arc::new(Server::http("127.0.0.1:0").unwrap());
+        let base_url = format!("http://{}", server.server_addr().to_ip().unwrap());
+        let port = server.server_addr().to_ip().unwrap().port().to_string();
+        let requests = Arc::new(Mutex::new(vec![]));
+        let posts = Arc::new(Mutex::new(vec![]));
+
+        let (thread_server, thread_requests, thread_posts, thread_base_url) = (server.clone(), requests.clone(), posts.clone(), base_url.clone());
+        thread::spawn(move || {
+            let mut answered = vec![0; routes.len()];
+            for mut request in thread_server.incoming_requests() {
+                let url = request.url().to_string();
+                let method = request.method().as_str().to_string();
+                thread_requests.lock().unwrap().push(url.clone());
+
+                if method == "POST" {
+                    let mut body = String::new();
+                    request.as_reader().read_to_string(&mut body).unwrap();
+                    // Token requests are form encoded rather than JSON.
+                    let body = serde_json::from_str(&body).unwrap_or(serde_json::Value::String(body));
+                    thread_posts.lock().unwrap().push((url.clone(), body));
+                }
+
+                let authorized = request.headers().iter()
+                    .any(|h| h.field.equiv("Authorization") && h.value.as_str() == format!("Bearer {}", TOKEN));
+
+                let index = routes.iter().enumerate().position(|(i, route)| {
+                    route.url == url && route.method == method && route.times.is_none_or(|times| answered[i] < times)
+                });
+                let rejected = !authorized && index.is_none_or(|i| routes[i].authorized);
+                let response = match index {
+                    _ if rejected => Response::from_string(r#"{"error":{"code":"InvalidAuthenticationToken","message":"Access token is empty."}}"#)
+                        .with_status_code(401),
+                    Some(i) => {
+                        answered[i] += 1;
+                        let route = &routes[i];
+                        let mut response = Response::from_string(fixture(&route.fixture).replace("{{base}}", &thread_base_url).replace("{{port}}", &port))
+                            .with_status_code(route.status)
+                            .with_header(Header::from_bytes("Content-Type", route.content_type).unwrap());
+                        for (name, value) in &route.headers {
+                            response.add_header(Header::from_bytes(*name, *value).unwrap());
+                        }
+                        response
+                    }
+                    None => Response::from_string(r#"{"error":{"code":"itemNotFound","message":"No stub for this url."}}"#)
+                        .with_status_code(404),
+                };
+                let _ = request.respond(response);
+            }
+        });
+
+        GraphStub { base_url, server, requests, posts }
+    }
+
+    /// The urls of every request received so far.
+    pub fn requests(&self) -> Vec<String> {
+        self.requests.lock().unwrap().clone()
+    }
+
+    /// The url and JSON body of every `POST` received so far.
+    pub fn posts(&self) -> Vec<(String, serde_json::Value)> {
+        self.posts.lock().unwrap().clone()
+    }
+
+    /// Run the exporter with `args` in `dir`, against the stub, whether it succeeds or not.
+    ///
+    /// Failing requests are not retried, unless `args` set `--max-attempts`. The stub's access token is
+    /// passed, unless `args` set a `--token-cache` to take the tokens from.
+    pub fn try_run(&self, dir: &Path, args: &[&str]) -> Output {
+        let mut command = Command::new(env!("CARGO_BIN_EXE_microsoft-todo-export"));
+        command.current_dir(dir).args(["--graph-url", &self.base_url]);
+        if !args.contains(&"--token-cache") {
+            command.args(["--token", TOKEN]);
+        }
+        if !args.contains(&"--max-attempts") {
+            command.args(["--max-attempts", "1"]);
+        }
+        command.args(args).output().unwrap()
+    }
+
+    /// Run the exporter with `args` in `dir`, against the stub, and check that it succeeds.
+    pub fn run(&self, dir: &Path, args: &[&str]) -> Output {
+        let output = self.try_run(dir, args);
+        assert!(
+            output.status.success(),
+            "exporter failed:\n{}\n{}",
+            String::from_utf8_lossy(&output.stdout),
+            String::from_utf8_lossy(&output.stderr)
+        );
+        output
+    }
+}
+
+impl Drop for GraphStub {
+    fn drop(&mut self) {
+        self.server.unblock();
+    }
+}
+
+pub fn fixture_path(name: &str) -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("tests").join("fixtures").join(name)
+}
+
+pub fn fixture(name: &str) -> String {
+    fs::read_to_string(fixture_path(name)).unwrap()
+}
+
+pub fn read_json(path: &Path) -> serde_json::Value {
+    serde_json::from_str(&fs::read_to_string(path).unwrap()).unwrap()
+}
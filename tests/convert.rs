@@ -0,0 +1,39 @@
+mod common;
+
+use std::fs;
+
+use common::GraphStub;
+
+#[test]
+fn converts_downloaded_pages_to_markdown() {
+    let stub = GraphStub::start(vec![]);
+    let dir = tempfile::tempdir().unwrap();
+    let content = dir.path().join("content");
+    fs::create_dir(&content).unwrap();
+    fs::copy(common::fixture_path("convert/trip.html"), content.join("trip.html")).unwrap();
+    fs::copy(common::fixture_path("onenote/page-4.html"), content.join("page-4.html")).unwrap();
+    fs::write(content.join("manifest.json"), "{}").unwrap();
+
+    let output = stub.run(dir.path(), &["convert", "--input", "content", "--output", "markdown"]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("2 pages converted"), "{}", stdout);
+
+    let markdown = dir.path().join("markdown");
+    assert_eq!(fs::read_to_string(markdown.join("trip.md")).unwrap(), common::fixture("convert/trip.md"));
+    assert!(fs::read_to_string(markdown.join("page-4.md")).unwrap().contains("- [x] Flour"));
+    assert!(!markdown.join("manifest.md").exists());
+}
+
+#[test]
+fn escapes_text_and_converts_outlines_in_their_layout_order() {
+    let stub = GraphStub::start(vec![]);
+    let dir = tempfile::tempdir().unwrap();
+    let content = dir.path().join("content");
+    fs::create_dir(&content).unwrap();
+    fs::copy(common::fixture_path("convert/layout.html"), content.join("layout.html")).unwrap();
+
+    stub.run(dir.path(), &["convert", "--input", "content", "--output", "markdown"]);
+
+    let markdown = fs::read_to_string(dir.path().join("markdown/layout.md")).unwrap();
+    assert_eq!(markdown, common::fixture("convert/layout.md"));
+}
@@ -0,0 +1,189 @@
+mod common;
+
+use std::fs;
+
+use common::{error, json, post, GraphStub, Route};
+
+fn import_routes() -> Vec<Route> {
+    vec![
+        json("/v1.0/me/todo/lists", "todo/lists.json"),
+        post("/v1.0/me/todo/lists", "import/created-list.json"),
+        post("/v1.0/me/todo/lists/list-tasks/tasks", "import/created-task-1.json"),
+        post("/v1.0/me/todo/lists/new-list-groceries/tasks", "import/created-task-2.json"),
+        post("/v1.0/me/todo/lists/list-tasks/tasks/new-task-1/checklistItems", "import/created-checklist-item.json"),
+    ]
+}
+
+fn import_stub() -> GraphStub {
+    GraphStub::start(import_routes())
+}
+
+/// Answer the first `POST` to `url` with `status`.
+fn failed_post(url: &str, status: u16) -> Route {
+    Route { method: "POST", ..error(url, status, "errors/unavailable.json").header("Retry-After", "0").times(1) }
+}
+
+#[test]
+fn import_recreates_lists_tasks_and_checklist_items() {
+    let stub = import_stub();
+    let dir = tempfile::tempdir().unwrap();
+    fs::copy(common::fixture_path("import/export.json"), dir.path().join("output.json")).unwrap();
+
+    stub.run(dir.path(), &["todo", "import"]);
+
+    let posts = stub.posts();
+    let urls: Vec<&str> = posts.iter().map(|(url, _)| url.as_str()).collect();
+    assert_eq!(urls, vec![
+        "/v1.0/me/todo/lists/list-tasks/tasks",
+        "/v1.0/me/todo/lists/list-tasks/tasks/new-task-1/checklistItems",
+        "/v1.0/me/todo/lists/list-tasks/tasks/new-task-1/checklistItems",
+        "/v1.0/me/todo/lists",
+        "/v1.0/me/todo/lists/new-list-groceries/tasks",
+    ], "the default list is imported into the existing default list");
+
+    let tax = &posts[0].1;
+    assert_eq!(tax["title"], "File the tax return");
+    assert_eq!(tax["importance"], "high");
+    assert_eq!(tax["isReminderOn"], true);
+    assert_eq!(tax["reminderDateTime"]["dateTime"], "2021-04-14T08:00:00.0000000");
+    assert_eq!(tax["dueDateTime"]["dateTime"], "2021-04-15T00:00:00.0000000");
+    assert_eq!(tax["recurrence"]["pattern"]["type"], "absoluteYearly");
+    assert_eq!(tax["recurrence"]["pattern"]["dayOfMonth"], 15);
+    assert_eq!(tax["createdDateTime"], "2021-03-01T08:00:00.0000000Z");
+    assert!(tax.get("id").is_none(), "read-only properties are not sent");
+
+    assert_eq!(posts[1].1["displayName"], "Collect the payslips");
+    assert_eq!(posts[1].1["isChecked"], true);
+    assert_eq!(posts[3].1["displayName"], "Groceries");
+    assert_eq!(posts[4].1["status"], "completed");
+
+    let ids = common::read_json(&dir.path().join("import-ids.json"));
+    assert_eq!(ids["lists"]["old-list-groceries"], "new-list-groceries");
+    assert_eq!(ids["tasks"]["old-task-2"], "new-task-2");
+    assert_eq!(ids["checklistItems"]["old-checklist-2"], "new-checklist-item");
+    assert!(!dir.path().join("import-ids.json.part").exists());
+}
+
+#[test]
+fn import_resumes_without_duplicates() {
+    let stub = import_stub();
+    let dir = tempfile::tempdir().unwrap();
+    fs::copy(common::fixture_path("import/export.json"), dir.path().join("output.json")).unwrap();
+
+    // An earlier run was interrupted after creating the first task and its first step.
+    fs::write(dir.path().join("import-ids.json"), r#"{
+        "lists": { "old-list-tasks": "list-tasks" },
+        "tasks": { "old-task-1": "new-task-1" },
+        "checklistItems": { "old-checklist-1": "new-checklist-item" }
+    }"#).unwrap();
+
+    stub.run(dir.path(), &["todo", "import"]);
+
+    let posts = stub.posts();
+    let names: Vec<&str> = posts.iter()
+        .map(|(_, body)| body.get("title").or_else(|| body.get("displayName")).unwrap().as_str().unwrap())
+        .collect();
+    assert_eq!(names, vec!["Fill in the forms", "Groceries", "Oat milk"]);
+
+    stub.run(dir.path(), &["todo", "import"]);
+    assert_eq!(stub.posts().len(), 3, "a finished import creates nothing more");
+}
+
+#[test]
+fn import_leaves_out_values_graph_would_reject() {
+    let stub = import_stub();
+    let dir = tempfile::tempdir().unwrap();
+    let mut export = common::read_json(&common::fixture_path("import/export.json"));
+    let task = &mut export[0]["children"][0];
+    task["body"]["contentType"] = "markdown".into();
+    task["recurrence"]["pattern"] = serde_json::json!({
+        "type": "weekly", "interval": 1, "daysOfWeek": ["monday", "someday"], "firstDayOfWeek": "someday"
+    });
+    export[1]["children"][0]["recurrence"] = serde_json::json!({
+        "pattern": { "type": "weekly", "interval": 1, "daysOfWeek": ["someday"], "firstDayOfWeek": "sunday" },
+        "range": { "type": "noEnd", "startDate": "2021-04-15", "recurrenceTimeZone": null }
+    });
+    fs::write(dir.path().join("output.json"), export.to_string()).unwrap();
+
+    stub.run(dir.path(), &["todo", "import"]);
+
+    let posts = stub.posts();
+    let tax = &posts[0].1;
+    assert_eq!(tax["body"]["contentType"], "text");
+    assert_eq!(tax["recurrence"]["pattern"]["daysOfWeek"], serde_json::json!(["monday"]));
+    assert_eq!(tax["recurrence"]["pattern"]["firstDayOfWeek"], "sunday");
+    assert!(posts[4].1.get("recurrence").is_none(), "a recurrence without a known day is left out");
+}
+
+#[test]
+fn import_retries_throttled_posts() {
+    let mut routes = vec![failed_post("/v1.0/me/todo/lists/list-tasks/tasks", 429)];
+    routes.extend(import_routes());
+    let stub = GraphStub::start(routes);
+    let dir = tempfile::tempdir().unwrap();
+    fs::copy(common::fixture_path("import/export.json"), dir.path().join("output.json")).unwrap();
+
+    stub.run(dir.path(), &["todo", "import", "--max-attempts", "2"]);
+
+    let tasks = stub.posts().iter().filter(|(url, _)| url == "/v1.0/me/todo/lists/list-tasks/tasks").count();
+    assert_eq!(tasks, 2, "the throttled task is sent again");
+    let ids = common::read_json(&dir.path().join("import-ids.json"));
+    assert_eq!(ids["tasks"]["old-task-1"], "new-task-1");
+}
+
+#[test]
+fn import_does_not_retry_posts_which_may_have_been_processed() {
+    let mut routes = vec![failed_post("/v1.0/me/todo/lists/list-tasks/tasks", 504)];
+    routes.extend(import_routes());
+    let stub = GraphStub::start(routes);
+    let dir = tempfile::tempdir().unwrap();
+    fs::copy(common::fixture_path("import/export.json"), dir.path().join("output.json")).unwrap();
+
+    let output = stub.try_run(dir.path(), &["todo", "import", "--max-attempts", "3"]);
+
+    assert!(!output.status.success());
+    assert_eq!(stub.posts().len(), 1, "the task may have been created, so it isn't sent again");
+}
+
+#[test]
+fn migrate_copies_every_list_into_the_target_account() {
+    let source = GraphStub::start(vec![
+        json("/v1.0/me/todo/lists", "todo/lists.json"),
+        json("/v1.0/me/todo/lists/list-tasks/tasks?$expand=checklistItems", "todo/tasks-page-1.json"),
+        json("/v1.0/me/todo/lists/list-tasks/tasks?$skip=2", "todo/tasks-page-2.json"),
+        json("/v1.0/me/todo/lists/list-groceries/tasks?$expand=checklistItems", "todo/groceries-tasks.json"),
+    ]);
+    let target = import_stub();
+    let dir = tempfile::tempdir().unwrap();
+
+    source.run(dir.path(), &["todo", "migrate", "--target-token", common::TOKEN, "--target-graph-url", &target.base_url]);
+
+    assert!(source.posts().is_empty(), "nothing is written to the source account");
+    assert_eq!(source.requests().len(), 4, "only the lists and tasks, with their steps, are requested");
+    assert!(!dir.path().join("attachments").exists());
+
+    let posts = target.posts();
+    let titles: Vec<(&str, &str)> = posts.iter()
+        .filter_map(|(url, body)| Some((url.as_str(), body.get("title")?.as_str()?)))
+        .collect();
+    assert_eq!(titles, vec![
+        ("/v1.0/me/todo/lists/list-tasks/tasks", "File the tax return"),
+        ("/v1.0/me/todo/lists/list-tasks/tasks", "Water the plants"),
+        ("/v1.0/me/todo/lists/list-tasks/tasks", "Read the manual"),
+        ("/v1.0/me/todo/lists/new-list-groceries/tasks", "Oat milk"),
+    ]);
+
+    let plants = &posts.iter().find(|(_, body)| body["title"] == "Water the plants").unwrap().1;
+    assert_eq!(plants["status"], "completed");
+    assert_eq!(plants["completedDateTime"]["dateTime"], "2021-03-10T00:00:00.0000000");
+    assert_eq!(plants["createdDateTime"], "2021-03-03T08:00:00.0000000Z");
+    assert_eq!(plants["lastModifiedDateTime"], "2021-03-10T18:00:00.0000000Z");
+
+    let manual = &posts.iter().find(|(_, body)| body["title"] == "Read the manual").unwrap().1;
+    assert!(manual.get("status").is_none(), "an unknown status is left to the target's default");
+    assert!(manual.get("recurrence").is_none());
+
+    let ids = common::read_json(&dir.path().join("migrate-ids.json"));
+    assert_eq!(ids["lists"]["list-tasks"], "list-tasks");
+    assert_eq!(ids["lists"]["list-groceries"], "new-list-groceries");
+}
@@ -0,0 +1,492 @@
+mod common;
+
+use std::fs;
+
+use common::{error, file, html, json, GraphStub, Route};
+
+fn onenote_routes() -> Vec<Route> {
+    vec![
+        // The notebook tree.
+        json("/v1.0/me/onenote/notebooks", "onenote/notebooks.json"),
+        json("/v1.0/me/onenote/notebooks/notebook-writing/sections", "onenote/writing-sections.json"),
+        json("/v1.0/me/onenote/notebooks/notebook-writing/sectionGroups", "todo/empty.json"),
+        json("/v1.0/me/onenote/notebooks/notebook-home/sections", "todo/empty.json"),
+        json("/v1.0/me/onenote/notebooks/notebook-home/sectionGroups", "onenote/home-section-groups.json"),
+        json("/v1.0/me/onenote/sectionGroups/group-kitchen/sections", "onenote/kitchen-sections.json"),
+        json("/v1.0/me/onenote/sectionGroups/group-kitchen/sectionGroups", "onenote/kitchen-section-groups.json"),
+        json("/v1.0/me/onenote/sectionGroups/group-baking/sections", "onenote/baking-sections.json"),
+        json("/v1.0/me/onenote/sectionGroups/group-baking/sectionGroups", "todo/empty.json"),
+        json("/v1.0/me/onenote/sections/section-bread/pages", "todo/empty.json"),
+
+        // The flat section listing.
+        json("/v1.0/me/onenote/sections", "onenote/sections.json"),
+        json("/v1.0/me/onenote/sectionGroups", "onenote/section-groups.json"),
+        json("/v1.0/me/onenote/sections/section-articles/pages", "onenote/articles-pages-1.json"),
+        json("/v1.0/me/onenote/sections/section-articles/pages?$skip=2", "onenote/articles-pages-2.json"),
+        json("/v1.0/me/onenote/sections/section-recipes/pages", "onenote/recipes-pages.json"),
+        html("/v1.0/me/onenote/pages/page-1/content", "onenote/page-1.html"),
+        html("/v1.0/me/onenote/pages/page-2/content", "onenote/page-2.html"),
+        html("/v1.0/me/onenote/pages/page-3/content", "onenote/page-3.html"),
+        html("/v1.0/me/onenote/pages/page-4/content", "onenote/page-4.html"),
+    ]
+}
+
+fn onenote_stub() -> GraphStub {
+    GraphStub::start(onenote_routes())
+}
+
+#[test]
+fn index_writes_every_notebook_with_its_section_groups_sections_and_pages() {
+    let stub = onenote_stub();
+    let dir = tempfile::tempdir().unwrap();
+
+    stub.run(dir.path(), &["onenote", "index"]);
+
+    let index = common::read_json(&dir.path().join("onenote-output.json"));
+    let notebooks = index["notebooks"].as_array().unwrap();
+    assert_eq!(notebooks.len(), 2);
+
+    assert_eq!(notebooks[0]["id"], "notebook-writing");
+    assert_eq!(notebooks[0]["sections"][0]["id"], "section-articles");
+    assert_eq!(notebooks[0]["sections"][0]["pages"].as_array().unwrap().len(), 3);
+
+    let kitchen = &notebooks[1]["sectionGroups"][0];
+    assert_eq!(kitchen["id"], "group-kitchen");
+    assert_eq!(kitchen["sections"][0]["pages"][0]["id"], "page-4");
+    assert_eq!(kitchen["sections"][0]["path"], serde_json::json!(["Home", "Kitchen"]));
+
+    let baking = &kitchen["sectionGroups"][0];
+    assert_eq!(baking["id"], "group-baking");
+    assert_eq!(baking["sections"][0]["id"], "section-bread");
+    assert_eq!(baking["sections"][0]["path"], serde_json::json!(["Home", "Kitchen", "Baking"]));
+    assert_eq!(baking["sections"][0]["parentName"], "Baking");
+
+    let urls = fs::read_to_string(dir.path().join("urls")).unwrap();
+    assert_eq!(urls.lines().count(), 4);
+}
+
+#[test]
+fn index_filters_nested_section_groups() {
+    let stub = onenote_stub();
+    let dir = tempfile::tempdir().unwrap();
+
+    stub.run(dir.path(), &["onenote", "index", "--section-group", "Baking"]);
+
+    let index = common::read_json(&dir.path().join("onenote-output.json"));
+    let notebooks = index["notebooks"].as_array().unwrap();
+    assert!(notebooks[0]["sections"].as_array().unwrap().is_empty());
+
+    let kitchen = &notebooks[1]["sectionGroups"][0];
+    assert!(kitchen["sections"].as_array().unwrap().is_empty(), "the parent group only leads to the matching one");
+    assert_eq!(kitchen["sectionGroups"][0]["sections"][0]["id"], "section-bread");
+    assert!(!stub.requests().iter().any(|url| url.contains("section-recipes")));
+
+    stub.run(dir.path(), &["onenote", "index", "--exclude-section-group", "Kitchen"]);
+
+    let index = common::read_json(&dir.path().join("onenote-output.json"));
+    assert!(index["notebooks"][1]["sectionGroups"].as_array().unwrap().is_empty());
+}
+
+#[test]
+fn flat_index_writes_every_section_with_its_pages() {
+    let stub = onenote_stub();
+    let dir = tempfile::tempdir().unwrap();
+
+    stub.run(dir.path(), &["onenote", "index", "--flat", "--output", "sections-output.json"]);
+
+    let sections = common::read_json(&dir.path().join("sections-output.json"));
+    let sections = sections.as_array().unwrap();
+    assert_eq!(sections.len(), 3);
+
+    assert_eq!(sections[0]["displayName"], "Articles");
+    let titles: Vec<&str> = sections[0]["pages"].as_array().unwrap().iter()
+        .map(|page| page["title"].as_str().unwrap())
+        .collect();
+    assert_eq!(titles, vec!["Article 1", "Article 2", "Article 3"]);
+
+    assert_eq!(sections[1]["displayName"], "Recipes");
+    assert_eq!(sections[1]["path"], serde_json::json!(["Home", "Kitchen"]));
+    assert_eq!(sections[1]["parentName"], "Kitchen");
+    assert_eq!(sections[1]["pages"][0]["id"], "page-4");
+    assert_eq!(sections[1]["pages"][0]["lastModifiedDateTime"], "2021-01-04T10:00:00Z");
+
+    assert_eq!(sections[2]["displayName"], "Bread");
+    assert_eq!(sections[2]["path"], serde_json::json!(["Home", "Kitchen", "Baking"]), "every group above a section is in its path");
+    assert_eq!(sections[2]["parentName"], "Baking");
+
+    let urls = fs::read_to_string(dir.path().join("urls")).unwrap();
+    assert_eq!(urls.lines().count(), 4);
+    assert!(urls.lines().all(|url| url.starts_with(&stub.base_url)));
+}
+
+#[test]
+fn index_passes_page_query_options() {
+    let query = "?$top=2&$orderby=title%20desc&$select=title,id,createdDateTime,lastModifiedDateTime,contentUrl";
+    let stub = GraphStub::start(vec![
+        json("/v1.0/me/onenote/sections", "onenote/sections.json"),
+        json("/v1.0/me/onenote/sectionGroups", "onenote/section-groups.json"),
+        json(&format!("/v1.0/me/onenote/sections/section-articles/pages{}", query), "onenote/articles-pages-1.json"),
+        json("/v1.0/me/onenote/sections/section-articles/pages?$skip=2", "onenote/articles-pages-2.json"),
+        json(&format!("/v1.0/me/onenote/sections/section-recipes/pages{}", query), "onenote/recipes-pages.json"),
+        json(&format!("/v1.0/me/onenote/sections/section-bread/pages{}", query), "todo/empty.json"),
+    ]);
+    let dir = tempfile::tempdir().unwrap();
+
+    stub.run(dir.path(), &["onenote", "index", "--flat", "--output", "sections-output.json", "--page-size", "2", "--order-by", "title desc", "--select", "title"]);
+
+    let sections = common::read_json(&dir.path().join("sections-output.json"));
+    assert_eq!(sections[0]["pages"].as_array().unwrap().len(), 3, "the next link is followed as given");
+    assert_eq!(sections[1]["pages"][0]["title"], "Pancakes");
+}
+
+#[test]
+fn index_applies_name_filters() {
+    let stub = onenote_stub();
+    let dir = tempfile::tempdir().unwrap();
+
+    stub.run(dir.path(), &["onenote", "index", "--flat", "--output", "sections-output.json", "--exclude-section-group", "Kitchen"]);
+
+    let sections = common::read_json(&dir.path().join("sections-output.json"));
+    assert_eq!(sections.as_array().unwrap().len(), 1);
+    assert_eq!(sections[0]["displayName"], "Articles");
+    assert!(!stub.requests().iter().any(|url| url.contains("section-recipes")));
+
+    stub.run(dir.path(), &["onenote", "index", "--flat", "--output", "sections-output.json", "--notebook", "re:^Ho"]);
+
+    let sections = common::read_json(&dir.path().join("sections-output.json"));
+    assert_eq!(sections.as_array().unwrap().len(), 2);
+    assert_eq!(sections[0]["displayName"], "Recipes");
+    assert_eq!(sections[1]["displayName"], "Bread");
+
+    // A group includes the sections of the groups nested in it, like in the notebook tree.
+    stub.run(dir.path(), &["onenote", "index", "--flat", "--output", "sections-output.json", "--section-group", "Kitchen"]);
+
+    let sections = common::read_json(&dir.path().join("sections-output.json"));
+    let names: Vec<&str> = sections.as_array().unwrap().iter().map(|section| section["displayName"].as_str().unwrap()).collect();
+    assert_eq!(names, vec!["Recipes", "Bread"]);
+
+    stub.run(dir.path(), &["onenote", "index", "--flat", "--output", "sections-output.json", "--section-group", "Baking"]);
+
+    let sections = common::read_json(&dir.path().join("sections-output.json"));
+    assert_eq!(sections.as_array().unwrap().len(), 1);
+    assert_eq!(sections[0]["displayName"], "Bread");
+}
+
+#[test]
+fn index_fails_when_a_listing_fails() {
+    let routes = onenote_routes().into_iter()
+        .filter(|route| route.url != "/v1.0/me/onenote/sectionGroups/group-kitchen/sections")
+        .collect();
+    let stub = GraphStub::start(routes);
+    let dir = tempfile::tempdir().unwrap();
+
+    let output = stub.try_run(dir.path(), &["onenote", "index"]);
+
+    assert!(!output.status.success());
+    assert!(!dir.path().join("onenote-output.json").exists(), "a partial index is not written");
+}
+
+#[test]
+fn download_pages_writes_the_content_of_every_page() {
+    let stub = onenote_stub();
+    let dir = tempfile::tempdir().unwrap();
+
+    stub.run(dir.path(), &["onenote", "index"]);
+    stub.run(dir.path(), &["onenote", "download-pages", "--jobs", "2"]);
+
+    for id in &["page-1", "page-2", "page-3", "page-4"] {
+        let content = fs::read_to_string(dir.path().join("content").join(format!("{}.html", id))).unwrap();
+        assert_eq!(content, common::fixture(&format!("onenote/{}.html", id)));
+    }
+}
+
+#[test]
+fn download_pages_reports_pages_which_fail() {
+    let stub = onenote_stub();
+    let dir = tempfile::tempdir().unwrap();
+
+    stub.run(dir.path(), &["onenote", "index"]);
+    let index = fs::read_to_string(dir.path().join("onenote-output.json")).unwrap();
+    fs::write(dir.path().join("onenote-output.json"), index.replace("page-2/content", "missing/content")).unwrap();
+
+    let output = stub.run(dir.path(), &["onenote", "download-pages"]);
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("1 pages failed to download"), "{}", stdout);
+    assert!(stdout.contains("page-2"));
+    assert!(dir.path().join("content").join("page-1.html").exists());
+    assert!(!dir.path().join("content").join("page-2.html").exists());
+}
+
+#[test]
+fn download_pages_waits_until_the_date_the_server_asks_for() {
+    let mut routes = vec![error("/v1.0/me/onenote/pages/page-2/content", 503, "errors/unavailable.json")
+        .header("Retry-After", "Wed, 21 Oct 2015 07:28:00 GMT")
+        .times(1)];
+    routes.extend(onenote_routes());
+    let stub = GraphStub::start(routes);
+    let dir = tempfile::tempdir().unwrap();
+
+    stub.run(dir.path(), &["onenote", "index"]);
+    let output = stub.run(dir.path(), &["onenote", "download-pages", "--max-attempts", "2"]);
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("request failed with 503 Service Unavailable, retrying in 0.0ns"), "{}", stdout);
+    assert!(dir.path().join("content").join("page-2.html").exists());
+}
+
+#[test]
+fn download_pages_waits_no_longer_than_the_max_delay() {
+    let mut routes = vec![error("/v1.0/me/onenote/pages/page-2/content", 429, "errors/throttled.json")
+        .header("Retry-After", "3600")
+        .times(1)];
+    routes.extend(onenote_routes());
+    let stub = GraphStub::start(routes);
+    let dir = tempfile::tempdir().unwrap();
+
+    stub.run(dir.path(), &["onenote", "index"]);
+    let output = stub.run(dir.path(), &["onenote", "download-pages", "--max-attempts", "2", "--max-delay", "0"]);
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("the server asked to wait 3600.0s, longer than --max-delay allows"), "{}", stdout);
+    assert!(dir.path().join("content").join("page-2.html").exists());
+}
+
+#[test]
+fn download_pages_reports_pages_which_stay_unavailable() {
+    let mut routes = vec![error("/v1.0/me/onenote/pages/page-2/content", 503, "errors/unavailable.json").header("Retry-After", "0")];
+    routes.extend(onenote_routes());
+    let stub = GraphStub::start(routes);
+    let dir = tempfile::tempdir().unwrap();
+
+    stub.run(dir.path(), &["onenote", "index"]);
+    let output = stub.run(dir.path(), &["onenote", "download-pages", "--max-attempts", "3"]);
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("1 pages failed to download"), "{}", stdout);
+    assert!(stdout.contains("page-2"));
+    assert_eq!(stub.requests().iter().filter(|url| url.contains("page-2/content")).count(), 3);
+    assert!(dir.path().join("content").join("page-1.html").exists());
+    assert!(!dir.path().join("content").join("page-2.html").exists());
+}
+
+#[test]
+fn download_pages_only_fetches_changed_pages() {
+    let stub = onenote_stub();
+    let dir = tempfile::tempdir().unwrap();
+    let content = dir.path().join("content");
+
+    stub.run(dir.path(), &["onenote", "index"]);
+    stub.run(dir.path(), &["onenote", "download-pages"]);
+    let manifest = common::read_json(&content.join("manifest.json"));
+    assert_eq!(manifest["pages"]["page-1"]["lastModifiedDateTime"], "2021-01-01T10:00:00Z");
+    assert_eq!(manifest["pages"]["page-1"]["file"], "page-1.html");
+
+    let downloads = |stub: &GraphStub| stub.requests().iter().filter(|url| url.ends_with("/content")).count();
+    assert_eq!(downloads(&stub), 4);
+
+    let output = stub.run(dir.path(), &["onenote", "download-pages"]);
+    assert_eq!(downloads(&stub), 4, "unchanged pages are not downloaded again");
+    assert!(String::from_utf8_lossy(&output.stdout).contains("0 pages downloaded, 4 up to date"));
+
+    // Page 1 changed upstream, page 2 was edited locally and page 4 was deleted upstream.
+    let mut index = common::read_json(&dir.path().join("onenote-output.json"));
+    index["notebooks"][0]["sections"][0]["pages"][0]["lastModifiedDateTime"] = "2021-02-01T10:00:00Z".into();
+    index["notebooks"][1]["sectionGroups"][0]["sections"][0]["pages"] = serde_json::json!([]);
+    fs::write(dir.path().join("onenote-output.json"), index.to_string()).unwrap();
+    fs::write(content.join("page-2.html"), "edited").unwrap();
+
+    stub.run(dir.path(), &["onenote", "download-pages"]);
+    assert_eq!(downloads(&stub), 6);
+    assert_eq!(fs::read_to_string(content.join("page-2.html")).unwrap(), common::fixture("onenote/page-2.html"));
+    assert!(!content.join("page-4.html").exists());
+    assert!(content.join("archive").join("page-4.html").exists());
+
+    let manifest = common::read_json(&content.join("manifest.json"));
+    assert_eq!(manifest["pages"]["page-1"]["lastModifiedDateTime"], "2021-02-01T10:00:00Z");
+    assert!(manifest["pages"].get("page-4").is_none());
+}
+
+#[test]
+fn download_pages_keeps_pages_left_out_by_filters() {
+    let stub = onenote_stub();
+    let dir = tempfile::tempdir().unwrap();
+    let content = dir.path().join("content");
+
+    stub.run(dir.path(), &["onenote", "index"]);
+    stub.run(dir.path(), &["onenote", "download-pages"]);
+    assert!(content.join("page-4.html").exists());
+
+    stub.run(dir.path(), &["onenote", "index", "--notebook", "Writing"]);
+    let output = stub.run(dir.path(), &["onenote", "download-pages"]);
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("1 no longer in the index"), "{}", stdout);
+    assert!(content.join("page-4.html").exists());
+    assert!(!content.join("archive").exists());
+
+    // They are only archived when asked to.
+    stub.run(dir.path(), &["onenote", "download-pages", "--removed", "archive"]);
+    assert!(content.join("archive").join("page-4.html").exists());
+}
+
+#[test]
+fn download_pages_archives_a_page_removed_again() {
+    let mut routes = vec![
+        html("/v1.0/me/onenote/pages/page-4/content", "onenote/page-resources.html"),
+        file("/v1.0/me/onenote/resources/image-1/$value", "todo/receipts.csv"),
+        file("/v1.0/me/onenote/resources/image-1-full/$value", "todo/receipts.csv"),
+        file("/v1.0/me/onenote/resources/image-2/$value", "todo/notes.txt"),
+        file("/v1.0/me/onenote/resources/file-1/$value", "todo/notes.txt"),
+    ];
+    routes.extend(onenote_routes());
+    let stub = GraphStub::start(routes);
+    let dir = tempfile::tempdir().unwrap();
+    let content = dir.path().join("content");
+    let archive = content.join("archive");
+
+    stub.run(dir.path(), &["onenote", "index"]);
+    let index = fs::read_to_string(dir.path().join("onenote-output.json")).unwrap();
+    let mut removed = common::read_json(&dir.path().join("onenote-output.json"));
+    removed["notebooks"][1]["sectionGroups"][0]["sections"][0]["pages"] = serde_json::json!([]);
+
+    // Page 4 is removed, comes back and is removed once more.
+    for _ in 0..2 {
+        fs::write(dir.path().join("onenote-output.json"), &index).unwrap();
+        stub.run(dir.path(), &["onenote", "download-pages"]);
+        fs::write(dir.path().join("onenote-output.json"), removed.to_string()).unwrap();
+        stub.run(dir.path(), &["onenote", "download-pages"]);
+    }
+
+    assert!(archive.join("page-4.html").exists());
+    assert!(archive.join("page-4_files").join("image-1.png").exists());
+    let again = archive.join("page-4 (2)");
+    assert!(again.join("page-4.html").exists(), "the earlier copy is not overwritten");
+    assert!(again.join("page-4_files").join("image-1.png").exists());
+
+    let manifest = common::read_json(&content.join("manifest.json"));
+    assert!(manifest["pages"].get("page-4").is_none());
+    assert!(manifest["pages"].get("page-1").is_some());
+}
+
+#[test]
+fn download_pages_saves_the_images_and_files_of_pages() {
+    let mut routes = vec![
+        html("/v1.0/me/onenote/pages/page-4/content", "onenote/page-resources.html"),
+        file("/v1.0/me/onenote/resources/image-1/$value", "todo/receipts.csv"),
+        file("/v1.0/me/onenote/resources/image-1-full/$value", "todo/receipts.csv"),
+        file("/v1.0/me/onenote/resources/image-2/$value", "todo/notes.txt"),
+        file("/v1.0/me/onenote/resources/image-3/$value", "todo/notes.txt"),
+        file("/v1.0/me/onenote/resources/file-1/$value", "todo/notes.txt"),
+    ];
+    routes.extend(onenote_routes());
+    let stub = GraphStub::start(routes);
+    let dir = tempfile::tempdir().unwrap();
+    let content = dir.path().join("content");
+
+    stub.run(dir.path(), &["onenote", "index"]);
+    stub.run(dir.path(), &["onenote", "download-pages"]);
+
+    let resources = content.join("page-4_files");
+    assert_eq!(fs::read(resources.join("image-1.png")).unwrap(), fs::read(common::fixture_path("todo/receipts.csv")).unwrap());
+    assert!(resources.join("image-2.jpg").exists());
+    assert!(resources.join("Shopping list.txt").exists());
+    assert!(!content.join("page-1_files").exists(), "pages without resources get no directory");
+
+    let page = fs::read_to_string(content.join("page-4.html")).unwrap();
+    assert!(page.contains(r#"src="page-4_files/image-1.png""#), "{}", page);
+    assert!(page.contains(r#"data="page-4_files/Shopping list.txt""#), "{}", page);
+    assert!(page.contains(r#"data-fullres-src="page-4_files/image-1-full.png""#), "{}", page);
+    assert!(resources.join("image-1-full.png").exists());
+    assert!(!page.contains(&format!("{}/v1.0/me/onenote/resources/", stub.base_url)));
+
+    // Urls on any other host are left alone, and never sent the access token.
+    assert!(page.contains("localhost:"), "{}", page);
+    assert!(!resources.join("image-3.png").exists());
+    assert!(!stub.requests().iter().any(|url| url.contains("image-3")));
+
+    stub.run(dir.path(), &["convert"]);
+
+    let markdown = fs::read_to_string(dir.path().join("out").join("page-4.md")).unwrap();
+    assert!(markdown.contains("![Batter](page-4_files/image-1.png)"), "{}", markdown);
+    assert!(markdown.contains("[Shopping list.txt](<page-4_files/Shopping list.txt>)"), "{}", markdown);
+    assert!(dir.path().join("out").join("page-4_files").join("Shopping list.txt").exists());
+}
+
+#[test]
+fn index_tolerates_missing_properties() {
+    let mut routes = vec![
+        json("/v1.0/me/onenote/sectionGroups/group-kitchen/sections", "onenote/sparse-sections.json"),
+        json("/v1.0/me/onenote/sections/section-recipes/pages", "onenote/sparse-pages.json"),
+    ];
+    routes.extend(onenote_routes());
+    let stub = GraphStub::start(routes);
+    let dir = tempfile::tempdir().unwrap();
+
+    stub.run(dir.path(), &["onenote", "index"]);
+
+    let index = common::read_json(&dir.path().join("onenote-output.json"));
+    let recipes = &index["notebooks"][1]["sectionGroups"][0]["sections"][0];
+    assert_eq!(recipes["displayName"], "");
+    let pages = recipes["pages"].as_array().unwrap();
+    assert_eq!(pages.len(), 1, "pages without content can't be downloaded");
+    assert_eq!(pages[0]["id"], "page-4");
+    assert_eq!(pages[0]["title"], "");
+}
+
+#[test]
+fn vault_writes_pages_into_a_directory_per_notebook_group_and_section() {
+    let mut routes = vec![
+        html("/v1.0/me/onenote/pages/page-4/content", "onenote/page-resources.html"),
+        file("/v1.0/me/onenote/resources/image-1/$value", "todo/receipts.csv"),
+        file("/v1.0/me/onenote/resources/image-1-full/$value", "todo/receipts.csv"),
+        file("/v1.0/me/onenote/resources/image-2/$value", "todo/notes.txt"),
+        file("/v1.0/me/onenote/resources/image-3/$value", "todo/notes.txt"),
+        file("/v1.0/me/onenote/resources/file-1/$value", "todo/notes.txt"),
+    ];
+    routes.extend(onenote_routes());
+    let stub = GraphStub::start(routes);
+    let dir = tempfile::tempdir().unwrap();
+
+    stub.run(dir.path(), &["onenote", "index"]);
+    stub.run(dir.path(), &["onenote", "download-pages"]);
+
+    // Two pages of a section with the same title.
+    let mut index = common::read_json(&dir.path().join("onenote-output.json"));
+    index["notebooks"][0]["sections"][0]["pages"][2]["title"] = "Article 1".into();
+    fs::write(dir.path().join("onenote-output.json"), index.to_string()).unwrap();
+
+    let output = stub.run(dir.path(), &["onenote", "vault"]);
+    assert!(String::from_utf8_lossy(&output.stdout).contains("4 pages written"));
+
+    let articles = dir.path().join("vault").join("Writing").join("Articles");
+    let article = fs::read_to_string(articles.join("Article 1.md")).unwrap();
+    assert!(article.starts_with("---\nid: page-1\ntitle: \"Article 1\"\ncreated: 2020-03-01T10:00:00Z\nmodified: 2021-01-01T10:00:00Z\n---\n\n"), "{}", article);
+    assert!(articles.join("Article 2.md").exists());
+    assert!(fs::read_to_string(articles.join("Article 1 (2).md")).unwrap().contains("id: page-3"));
+
+    let recipes = dir.path().join("vault").join("Home").join("Kitchen").join("Recipes");
+    let pancakes = fs::read_to_string(recipes.join("Pancakes.md")).unwrap();
+    assert!(pancakes.contains("![Batter](assets/page-4/image-1.png)"), "{}", pancakes);
+    assert!(pancakes.contains("[Shopping list.txt](<assets/page-4/Shopping list.txt>)"), "{}", pancakes);
+    assert!(recipes.join("assets").join("page-4").join("Shopping list.txt").exists());
+}
+
+#[test]
+fn quake_writes_downloaded_pages_as_entries() {
+    let stub = onenote_stub();
+    let dir = tempfile::tempdir().unwrap();
+
+    stub.run(dir.path(), &["onenote", "index"]);
+    stub.run(dir.path(), &["onenote", "download-pages"]);
+    stub.run(dir.path(), &["quake", "onenote", "--entry-type", "notes"]);
+
+    let entries = dir.path().join("quake").join("notes");
+    let article = fs::read_to_string(entries.join("0001-article-1.md")).unwrap();
+    assert!(article.starts_with("---\ntitle: \"Article 1\"\ncreated_date: 2020-03-01 10:00:00\n"), "{}", article);
+    assert!(entries.join("0004-pancakes.md").exists());
+
+    let csv = fs::read_to_string(entries.join("entries.csv")).unwrap();
+    assert_eq!(csv.lines().next(), Some("id,title,created_date,updated_date,source_id"));
+    assert_eq!(csv.lines().count(), 5);
+}
@@ -0,0 +1,99 @@
+mod common;
+
+use std::fs;
+
+use common::GraphStub;
+
+#[test]
+fn todo_entries_are_numbered_and_indexed() {
+    let stub = GraphStub::start(vec![]);
+    let dir = tempfile::tempdir().unwrap();
+    fs::copy(common::fixture_path("import/export.json"), dir.path().join("output.json")).unwrap();
+
+    stub.run(dir.path(), &["quake", "todo"]);
+
+    let entries = dir.path().join("quake").join("todo");
+    let task = fs::read_to_string(entries.join("0001-file-the-tax-return.md")).unwrap();
+    assert!(task.starts_with(concat!(
+        "---\n",
+        "title: \"File the tax return\"\n",
+        "created_date: 2021-03-01 08:00:00\n",
+        "updated_date: 2021-03-02 09:30:00\n",
+        "list: \"Tasks\"\n",
+        "status: \"notStarted\"\n",
+        "source_id: \"old-task-1\"\n",
+        "---\n\n",
+        "Receipts are in the blue folder\n",
+    )), "{}", task);
+    assert!(task.contains("- [x] "), "{}", task);
+    assert!(entries.join("0002-oat-milk.md").exists());
+
+    let csv = fs::read_to_string(entries.join("entries.csv")).unwrap();
+    assert_eq!(csv, concat!(
+        "id,title,created_date,updated_date,list,status,source_id\n",
+        "1,File the tax return,2021-03-01 08:00:00,2021-03-02 09:30:00,Tasks,notStarted,old-task-1\n",
+        "2,Oat milk,2021-03-05 08:00:00,2021-03-06 08:00:00,Groceries,completed,old-task-2\n",
+    ));
+
+    // Running again adds the new tasks, and updates the changed ones in place.
+    let mut export = common::read_json(&dir.path().join("output.json"));
+    export[0]["children"][0]["title"] = "File the tax return, finally".into();
+    export[1]["children"][0]["id"] = "new-task".into();
+    export[1]["children"][0]["title"] = "Oats, rolled".into();
+    fs::write(dir.path().join("output.json"), export.to_string()).unwrap();
+
+    let output = stub.run(dir.path(), &["quake", "todo"]);
+    assert!(String::from_utf8_lossy(&output.stdout).contains("1 entries written"));
+    assert!(String::from_utf8_lossy(&output.stdout).contains("1 updated"));
+    assert!(entries.join("0003-oats-rolled.md").exists());
+    assert!(!entries.join("0001-file-the-tax-return.md").exists());
+    let task = fs::read_to_string(entries.join("0001-file-the-tax-return-finally.md")).unwrap();
+    assert!(task.contains("title: \"File the tax return, finally\"\n"), "{}", task);
+
+    let csv = fs::read_to_string(entries.join("entries.csv")).unwrap();
+    assert_eq!(csv, concat!(
+        "id,title,created_date,updated_date,list,status,source_id\n",
+        "1,\"File the tax return, finally\",2021-03-01 08:00:00,2021-03-02 09:30:00,Tasks,notStarted,old-task-1\n",
+        "2,Oat milk,2021-03-05 08:00:00,2021-03-06 08:00:00,Groceries,completed,old-task-2\n",
+        "3,\"Oats, rolled\",2021-03-05 08:00:00,2021-03-06 08:00:00,Groceries,completed,new-task\n",
+    ));
+
+    let output = stub.run(dir.path(), &["quake", "todo"]);
+    assert!(String::from_utf8_lossy(&output.stdout).contains("0 entries written"));
+    assert!(String::from_utf8_lossy(&output.stdout).contains("0 updated, 2 unchanged"));
+}
+
+#[test]
+fn entries_with_other_columns_are_refused() {
+    let stub = GraphStub::start(vec![]);
+    let dir = tempfile::tempdir().unwrap();
+    let entries = dir.path().join("quake").join("notes");
+    fs::create_dir_all(&entries).unwrap();
+    fs::write(entries.join("entries.csv"), "id,title,created_date,updated_date,source_id\n").unwrap();
+    fs::copy(common::fixture_path("import/export.json"), dir.path().join("output.json")).unwrap();
+
+    let output = stub.try_run(dir.path(), &["quake", "todo", "--entry-type", "notes"]);
+
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("another entry type"), "{}", String::from_utf8_lossy(&output.stderr));
+    assert!(!entries.join("0001-file-the-tax-return.md").exists());
+}
+
+#[test]
+fn the_index_gets_a_header_when_a_first_run_wrote_nothing() {
+    let stub = GraphStub::start(vec![]);
+    let dir = tempfile::tempdir().unwrap();
+    let entries = dir.path().join("quake").join("todo");
+
+    fs::write(dir.path().join("output.json"), "[]").unwrap();
+    stub.run(dir.path(), &["quake", "todo"]);
+    assert!(!entries.join("entries.csv").exists());
+
+    // An empty index, as left by earlier versions, gets its header too.
+    fs::write(entries.join("entries.csv"), "").unwrap();
+    fs::copy(common::fixture_path("import/export.json"), dir.path().join("output.json")).unwrap();
+    stub.run(dir.path(), &["quake", "todo"]);
+
+    let csv = fs::read_to_string(entries.join("entries.csv")).unwrap();
+    assert!(csv.starts_with("id,title,created_date,updated_date,list,status,source_id\n1,File the tax return,"), "{}", csv);
+}
@@ -0,0 +1,97 @@
+mod common;
+
+use common::{json, GraphStub};
+
+fn sync_stub() -> GraphStub {
+    let mut routes = vec![
+        // The first sync.
+        json("/v1.0/me/todo/lists/delta", "sync/lists-delta.json"),
+        json("/v1.0/me/todo/lists/list-tasks/tasks/delta", "sync/tasks-delta-1.json"),
+        json("/v1.0/me/todo/lists/list-tasks/tasks/delta?$skiptoken=page-2", "sync/tasks-delta-2.json"),
+        json("/v1.0/me/todo/lists/list-groceries/tasks/delta", "sync/groceries-delta.json"),
+
+        // The second sync, from the delta links of the first.
+        json("/v1.0/me/todo/lists/delta?$deltatoken=lists-2", "sync/lists-delta-2.json"),
+        json("/v1.0/me/todo/lists/list-tasks/tasks/delta?$deltatoken=tasks-2", "sync/tasks-delta-changes.json"),
+    ];
+    routes.extend(common::empty_task_relations(&[
+        ("list-tasks", "task-1"),
+        ("list-tasks", "task-2"),
+        ("list-tasks", "task-3"),
+        ("list-tasks", "task-5"),
+        ("list-groceries", "task-4"),
+    ]));
+
+    GraphStub::start(routes)
+}
+
+fn task_titles(list: &serde_json::Value) -> Vec<&str> {
+    list["children"].as_array().unwrap().iter()
+        .map(|task| task["title"].as_str().unwrap())
+        .collect()
+}
+
+#[test]
+fn first_sync_writes_every_list_and_the_delta_links() {
+    let stub = sync_stub();
+    let dir = tempfile::tempdir().unwrap();
+
+    stub.run(dir.path(), &["todo", "sync"]);
+
+    let output = common::read_json(&dir.path().join("output.json"));
+    assert_eq!(output.as_array().unwrap().len(), 2);
+    assert_eq!(task_titles(&output[0]), vec!["File the tax return", "Water the plants", "Read the manual"]);
+    assert_eq!(task_titles(&output[1]), vec!["Oat milk"]);
+
+    let state = common::read_json(&dir.path().join("sync-state.json"));
+    assert_eq!(state["lists"], format!("{}/v1.0/me/todo/lists/delta?$deltatoken=lists-2", stub.base_url));
+    assert_eq!(state["tasks"]["list-tasks"], format!("{}/v1.0/me/todo/lists/list-tasks/tasks/delta?$deltatoken=tasks-2", stub.base_url));
+}
+
+#[test]
+fn later_syncs_only_apply_the_changes() {
+    let stub = sync_stub();
+    let dir = tempfile::tempdir().unwrap();
+
+    stub.run(dir.path(), &["todo", "sync"]);
+    let first_requests = stub.requests().len();
+
+    let output = stub.run(dir.path(), &["todo", "sync"]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("Tasks: 1 added, 1 changed, 1 removed"), "{}", stdout);
+
+    let output = common::read_json(&dir.path().join("output.json"));
+    let lists = output.as_array().unwrap();
+    assert_eq!(lists.len(), 1, "the removed list is dropped");
+    assert_eq!(task_titles(&lists[0]), vec!["File the tax return", "Water the plants", "Book the dentist"]);
+    assert_eq!(lists[0]["children"][1]["status"], "completed");
+
+    let requests = stub.requests();
+    let second: Vec<&String> = requests[first_requests..].iter().filter(|url| url.contains("delta")).collect();
+    assert_eq!(second, vec![
+        "/v1.0/me/todo/lists/delta?$deltatoken=lists-2",
+        "/v1.0/me/todo/lists/list-tasks/tasks/delta?$deltatoken=tasks-2",
+    ]);
+    assert!(!requests[first_requests..].iter().any(|url| url.contains("task-1")), "unchanged tasks are not requested");
+
+    let state = common::read_json(&dir.path().join("sync-state.json"));
+    assert!(state["tasks"].get("list-groceries").is_none());
+    assert_eq!(state["tasks"]["list-tasks"], format!("{}/v1.0/me/todo/lists/list-tasks/tasks/delta?$deltatoken=tasks-3", stub.base_url));
+}
+
+#[test]
+fn a_missing_export_is_synced_from_scratch() {
+    let stub = sync_stub();
+    let dir = tempfile::tempdir().unwrap();
+
+    stub.run(dir.path(), &["todo", "sync"]);
+    std::fs::remove_file(dir.path().join("output.json")).unwrap();
+    let first_requests = stub.requests().len();
+
+    stub.run(dir.path(), &["todo", "sync"]);
+
+    let output = common::read_json(&dir.path().join("output.json"));
+    assert_eq!(output.as_array().unwrap().len(), 2, "the saved delta links are not used without their export");
+    assert_eq!(task_titles(&output[0]), vec!["File the tax return", "Water the plants", "Read the manual"]);
+    assert!(stub.requests()[first_requests..].contains(&"/v1.0/me/todo/lists/delta".to_string()));
+}
@@ -0,0 +1,169 @@
+mod common;
+
+use common::{error, file, json, GraphStub, Route};
+use sha2::{Digest, Sha256};
+
+fn todo_routes() -> Vec<Route> {
+    vec![
+        json("/v1.0/me/todo/lists", "todo/lists.json"),
+        json("/v1.0/me/todo/lists/list-tasks/tasks?$expand=checklistItems,extensions,linkedResources", "todo/tasks-page-1.json"),
+        json("/v1.0/me/todo/lists/list-tasks/tasks?$skip=2", "todo/tasks-page-2.json"),
+        json("/v1.0/me/todo/lists/list-groceries/tasks?$expand=checklistItems,extensions,linkedResources", "todo/groceries-tasks.json"),
+        json("/v1.0/me/todo/lists/list-tasks/tasks/task-1/attachments", "todo/task-1-attachments.json"),
+        file("/v1.0/me/todo/lists/list-tasks/tasks/task-1/attachments/attachment-1/$value", "todo/receipts.csv"),
+        file("/v1.0/me/todo/lists/list-tasks/tasks/task-1/attachments/attachment-2/$value", "todo/notes.txt"),
+    ]
+}
+
+fn todo_stub() -> GraphStub {
+    GraphStub::start(todo_routes())
+}
+
+#[test]
+fn export_writes_every_list_with_its_tasks() {
+    let stub = todo_stub();
+    let dir = tempfile::tempdir().unwrap();
+
+    stub.run(dir.path(), &["todo", "export"]);
+
+    let output = common::read_json(&dir.path().join("output.json"));
+    let lists = output.as_array().unwrap();
+    assert_eq!(lists.len(), 2);
+
+    assert_eq!(lists[0]["displayName"], "Tasks");
+    assert_eq!(lists[0]["wellknownListName"], "defaultList");
+    let titles: Vec<&str> = lists[0]["children"].as_array().unwrap().iter()
+        .map(|task| task["title"].as_str().unwrap())
+        .collect();
+    assert_eq!(titles, vec!["File the tax return", "Water the plants", "Read the manual"], "every page is read exactly once");
+
+    assert_eq!(lists[1]["displayName"], "Groceries");
+    assert_eq!(lists[1]["children"][0]["title"], "Oat milk");
+    assert_eq!(lists[1]["children"][0]["status"], "notStarted");
+
+    assert!(stub.requests().contains(&"/v1.0/me/todo/lists/list-tasks/tasks?$skip=2".to_string()));
+    assert!(
+        !stub.requests().iter().any(|url| url.ends_with("/checklistItems") || url.ends_with("/extensions") || url.ends_with("/linkedResources")),
+        "the relations of the tasks are expanded rather than requested one by one"
+    );
+}
+
+#[test]
+fn export_preserves_task_properties() {
+    let stub = todo_stub();
+    let dir = tempfile::tempdir().unwrap();
+
+    stub.run(dir.path(), &["todo", "export", "--output", "backup.json"]);
+
+    let output = common::read_json(&dir.path().join("backup.json"));
+    let tasks = output[0]["children"].as_array().unwrap();
+    let tax = tasks.iter().find(|task| task["id"] == "task-1").unwrap();
+    assert_eq!(tax["importance"], "high");
+    assert_eq!(tax["isReminderOn"], true);
+    assert_eq!(tax["dueDateTime"]["dateTime"], "2021-04-15T00:00:00.0000000");
+    assert_eq!(tax["body"]["content"], "Receipts are in the blue folder");
+
+    let plants = tasks.iter().find(|task| task["id"] == "task-2").unwrap();
+    assert_eq!(plants["recurrence"]["pattern"]["type"], "weekly");
+    assert_eq!(plants["recurrence"]["pattern"]["daysOfWeek"][0], "wednesday");
+    assert_eq!(plants["recurrence"]["range"]["type"], "noEnd");
+
+    let manual = tasks.iter().find(|task| task["id"] == "task-3").unwrap();
+    assert_eq!(manual["categories"], serde_json::json!(["Reading", "Work"]));
+    assert_eq!(manual["startDateTime"]["dateTime"], "2021-03-05T00:00:00.0000000");
+    assert_eq!(manual["bodyLastModifiedDateTime"], "2021-03-04T08:00:00.0000000Z");
+    assert_eq!(manual["isReminderOn"], false, "a missing isReminderOn means no reminder");
+    assert_eq!(manual["status"], "unknown", "an unexpected status doesn't abort the export");
+    assert_eq!(manual["recurrence"]["pattern"]["type"], "unknown");
+}
+
+#[test]
+fn export_nests_checklist_items_in_their_task() {
+    let stub = todo_stub();
+    let dir = tempfile::tempdir().unwrap();
+
+    stub.run(dir.path(), &["todo", "export"]);
+
+    let output = common::read_json(&dir.path().join("output.json"));
+    let steps = output[0]["children"][0]["checklistItems"].as_array().unwrap();
+    assert_eq!(steps.len(), 2);
+    assert_eq!(steps[0]["displayName"], "Collect the payslips");
+    assert_eq!(steps[0]["isChecked"], true);
+    assert_eq!(steps[1]["checkedDateTime"], serde_json::Value::Null);
+
+    assert_eq!(output[1]["children"][0]["checklistItems"].as_array().unwrap().len(), 0);
+}
+
+#[test]
+fn export_includes_linked_resources_and_extensions() {
+    let stub = todo_stub();
+    let dir = tempfile::tempdir().unwrap();
+
+    stub.run(dir.path(), &["todo", "export"]);
+
+    let output = common::read_json(&dir.path().join("output.json"));
+    let task = &output[0]["children"][0];
+
+    let linked = task["linkedResources"].as_array().unwrap();
+    assert_eq!(linked.len(), 1);
+    assert_eq!(linked[0]["applicationName"], "Outlook");
+    assert_eq!(linked[0]["webUrl"], "https://outlook.office365.com/owa/?ItemID=AAMkAGI1");
+
+    let extensions = task["extensions"].as_array().unwrap();
+    assert_eq!(extensions[0]["extensionName"], "com.contoso.referral");
+    assert_eq!(extensions[0]["referralId"], "ref-42", "custom extension properties are kept");
+
+    assert_eq!(output[0]["children"][1]["linkedResources"].as_array().unwrap().len(), 0);
+}
+
+#[test]
+fn export_downloads_task_attachments() {
+    let stub = todo_stub();
+    let dir = tempfile::tempdir().unwrap();
+
+    stub.run(dir.path(), &["todo", "export"]);
+
+    let output = common::read_json(&dir.path().join("output.json"));
+    let attachments = output[0]["children"][0]["attachments"].as_array().unwrap();
+    assert_eq!(attachments.len(), 2);
+    assert_eq!(attachments[0]["contentType"], "text/csv");
+    assert_eq!(attachments[0]["size"], 52);
+
+    // The directory is keyed on the task id, so that it doesn't depend on the other tasks of the run.
+    let id_hash: String = Sha256::digest(b"task-1").iter().take(4).map(|b| format!("{:02x}", b)).collect();
+    let task_dir = dir.path().join("attachments").join("Tasks").join(format!("File the tax return ({})", id_hash));
+    assert_eq!(std::fs::read_to_string(task_dir.join("receipts_ march_april.csv")).unwrap(), common::fixture("todo/receipts.csv"));
+    assert_eq!(std::fs::read_to_string(task_dir.join("notes.txt")).unwrap(), common::fixture("todo/notes.txt"));
+    assert!(!stub.requests().iter().any(|url| url.contains("task-2/attachments")), "tasks without attachments are skipped");
+
+    let dir = tempfile::tempdir().unwrap();
+    stub.run(dir.path(), &["todo", "export", "--skip-attachments"]);
+    assert!(!dir.path().join("attachments").exists());
+}
+
+#[test]
+fn export_as_text_lists_task_titles() {
+    let stub = todo_stub();
+    let dir = tempfile::tempdir().unwrap();
+
+    stub.run(dir.path(), &["todo", "export", "--format", "text", "--output", "tasks.txt"]);
+
+    let text = std::fs::read_to_string(dir.path().join("tasks.txt")).unwrap();
+    assert!(text.starts_with("# Tasks\n"));
+    assert!(text.contains("# Groceries\nOat milk\n"));
+}
+
+#[test]
+fn export_retries_throttled_requests() {
+    let mut routes = vec![error("/v1.0/me/todo/lists", 429, "errors/throttled.json").header("Retry-After", "0").times(1)];
+    routes.extend(todo_routes());
+    let stub = GraphStub::start(routes);
+    let dir = tempfile::tempdir().unwrap();
+
+    let output = stub.run(dir.path(), &["todo", "export", "--max-attempts", "2"]);
+
+    assert!(String::from_utf8_lossy(&output.stdout).contains("retrying"));
+    let lists = common::read_json(&dir.path().join("output.json"));
+    assert_eq!(lists.as_array().unwrap().len(), 2);
+    assert_eq!(stub.requests().iter().filter(|url| *url == "/v1.0/me/todo/lists").count(), 2);
+}